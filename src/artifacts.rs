@@ -0,0 +1,331 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use toml::Value;
+
+use crate::deps::Source;
+use crate::targets::{self, Target, TargetKind};
+use crate::{workspace, ProjectInfo};
+
+/// The profile directories that are checked for built executables.
+pub const PROFILES: [&str; 2] = ["release", "debug"];
+
+/// The files and directories of a package that Cargo rebuilds it from.
+const BUILD_INPUTS: [&str; 3] = ["src", "build.rs", "Cargo.toml"];
+
+/// An executable that Cargo has built for a binary or example target.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    /// The name of the target the executable was built from.
+    pub target: String,
+    /// The kind of the target, `Bin` or `Example`.
+    pub kind: TargetKind,
+    /// The profile directory the executable is in, `debug` or `release`.
+    pub profile: &'static str,
+    /// The full path of the executable.
+    pub path: PathBuf,
+    /// The size of the executable in bytes.
+    pub size: u64,
+    /// When the executable was last written.
+    pub modified: SystemTime,
+    /// Whether a build input has changed since the executable was built; see `newest_build_input`.
+    pub stale: bool,
+}
+
+/// Returns every directory that may hold build artifacts of a package, most relevant first.
+///
+/// Cargo writes to `CARGO_TARGET_DIR` if it is set, otherwise to `CARGO_BUILD_TARGET_DIR` or
+/// `build.target-dir` from the nearest `.cargo/config.toml` (or `.cargo/config`) in the
+/// package directory, its ancestors or `$CARGO_HOME`, and otherwise to `target/` at the workspace root. Earlier builds may have
+/// used any of them, so all are returned; the first one is where the next build goes.
+///
+/// # Arguments
+///
+/// * `package_dir` - The package directory.
+///
+/// # Returns
+///
+/// The target directories without duplicates; the last one is always the default `target/`.
+pub fn target_dirs(package_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR").filter(|d| !d.is_empty()) {
+        let dir = PathBuf::from(dir);
+        dirs.push(env::current_dir().map(|cwd| cwd.join(&dir)).unwrap_or(dir));
+    }
+    if let Some(dir) = configured_target_dir(package_dir) {
+        dirs.push(dir);
+    }
+    dirs.push(targets::target_dir(package_dir));
+
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        let canonical = fs::canonicalize(&dir).unwrap_or(dir);
+        if !unique.contains(&canonical) {
+            unique.push(canonical);
+        }
+    }
+    unique
+}
+
+/// Reads `build.target-dir` from the Cargo configuration that applies to a directory.
+///
+/// The `CARGO_BUILD_TARGET_DIR` environment variable overrides the configuration files.
+/// Relative paths are resolved against the current directory for the variable and against
+/// the directory that contains the `.cargo` directory for the files, the way Cargo does.
+fn configured_target_dir(dir: &Path) -> Option<PathBuf> {
+    if let Some(target_dir) = env::var_os("CARGO_BUILD_TARGET_DIR").filter(|d| !d.is_empty()) {
+        let target_dir = PathBuf::from(target_dir);
+        return Some(env::current_dir().map(|cwd| cwd.join(&target_dir)).unwrap_or(target_dir));
+    }
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    let config_dirs = dir.ancestors().map(|d| d.join(".cargo")).chain(cargo_home);
+
+    for config_dir in config_dirs {
+        for file in ["config.toml", "config"] {
+            let path = config_dir.join(file);
+            let Some(config) = fs::read_to_string(&path).ok().and_then(|c| c.parse::<Value>().ok()) else {
+                continue;
+            };
+            if let Some(target_dir) = config.get("build").and_then(|b| b.get("target-dir")).and_then(|t| t.as_str()) {
+                let base = config_dir.parent().unwrap_or(&config_dir);
+                return Some(base.join(target_dir));
+            }
+            // Cargo ignores `config` when `config.toml` exists next to it.
+            break;
+        }
+    }
+    None
+}
+
+/// Finds the executables of a project that have actually been built.
+///
+/// Every binary and example target is looked up in the `debug` and `release` profile of
+/// each directory returned by `target_dirs`. An executable is stale when a build input was
+/// modified after it; see `newest_build_input`.
+///
+/// # Arguments
+///
+/// * `info` - The project whose executables to look for.
+///
+/// # Returns
+///
+/// The built executables, ordered by target, then profile (`release` first), then target directory.
+pub fn find(info: &ProjectInfo) -> Vec<Artifact> {
+    let runnable: Vec<&Target> = info
+        .targets
+        .iter()
+        .filter(|t| matches!(t.kind, TargetKind::Bin | TargetKind::Example))
+        .collect();
+    if runnable.is_empty() {
+        return Vec::new();
+    }
+
+    let dirs = target_dirs(&info.path);
+    let mut found = Vec::new();
+    for target in runnable {
+        for profile in PROFILES {
+            for dir in &dirs {
+                let Some(path) = targets::executable_path(dir, profile, target) else {
+                    continue;
+                };
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+                found.push(Artifact {
+                    target: target.name.clone(),
+                    kind: target.kind,
+                    profile,
+                    path,
+                    size: metadata.len(),
+                    modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    stale: false,
+                });
+            }
+        }
+    }
+
+    if !found.is_empty() {
+        let newest = newest_build_input(info);
+        for artifact in &mut found {
+            artifact.stale = newest.is_some_and(|newest| newest > artifact.modified);
+        }
+    }
+    found
+}
+
+/// Returns when a build input of a project was last modified.
+///
+/// The build inputs are `src/`, `build.rs` and `Cargo.toml` of the project, of its workspace
+/// members and of every package they depend on by path, directly or not, together with the
+/// directories holding binary and example sources declared elsewhere and the workspace's
+/// `Cargo.lock`. Other files, such as `README.md`, do not change what Cargo builds.
+///
+/// # Arguments
+///
+/// * `info` - The project whose executables are checked.
+///
+/// # Returns
+///
+/// The newest modification time, or `None` if no build input could be read.
+pub fn newest_build_input(info: &ProjectInfo) -> Option<SystemTime> {
+    let mut newest = None;
+    let workspace_root = workspace::enclosing_root(&info.path).unwrap_or_else(|| info.path.clone());
+    newer(&workspace_root.join("Cargo.lock"), &mut newest);
+
+    let mut seen = BTreeSet::new();
+    let mut pending = Vec::new();
+    for package in std::iter::once(info).chain(&info.members) {
+        pending.extend(package_inputs(package, &mut seen, &mut newest));
+    }
+    while let Some(dir) = pending.pop() {
+        if let Some(package) = crate::parse_cargo_toml(&dir.join("Cargo.toml")) {
+            pending.extend(package_inputs(&package, &mut seen, &mut newest));
+        }
+    }
+    newest
+}
+
+/// Adds the build inputs of one package for `newest_build_input`, unless it was seen before.
+///
+/// # Returns
+///
+/// The directories of the packages it depends on by path, which need checking in turn.
+fn package_inputs(package: &ProjectInfo, seen: &mut BTreeSet<PathBuf>, newest: &mut Option<SystemTime>) -> Vec<PathBuf> {
+    let dir = fs::canonicalize(&package.path).unwrap_or_else(|_| package.path.clone());
+    if !seen.insert(dir) {
+        return Vec::new();
+    }
+    for input in BUILD_INPUTS {
+        let path = package.path.join(input);
+        newer(&path, newest);
+        newest_in(&path, true, newest);
+    }
+    let runnable = package.targets.iter().filter(|t| matches!(t.kind, TargetKind::Bin | TargetKind::Example));
+    let mut source_dirs: Vec<PathBuf> = runnable
+        .filter_map(|target| target.path.parent().map(|dir| package.path.join(dir)))
+        .filter(|dir| !dir.starts_with(package.path.join("src")))
+        .collect();
+    source_dirs.dedup();
+    for dir in source_dirs {
+        newest_in(&dir, true, newest);
+    }
+    package
+        .dependencies
+        .iter()
+        .filter_map(|dependency| match &dependency.source {
+            Source::Path(path) => Some(path.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Keeps a file's modification time in `newest` if it is newer.
+fn newer(path: &Path, newest: &mut Option<SystemTime>) {
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        if newest.is_none_or(|n| modified > n) {
+            *newest = Some(modified);
+        }
+    }
+}
+
+/// Returns when a source file of a package was last modified.
+///
+/// Every file below the package directory counts, including `Cargo.toml`, `build.rs` and
+/// files pulled in with `include_str!`. Hidden directories, `target/` and nested packages
+/// (directories with their own `Cargo.toml`) are skipped.
+///
+/// # Arguments
+///
+/// * `dir` - The package directory.
+///
+/// # Returns
+///
+/// The newest modification time, or `None` if no file could be read.
+pub fn newest_source(dir: &Path) -> Option<SystemTime> {
+    let mut newest = None;
+    newest_in(dir, true, &mut newest);
+    newest
+}
+
+/// Walks a directory for `newest_source`, keeping the newest modification time seen so far.
+fn newest_in(dir: &Path, is_package_root: bool, newest: &mut Option<SystemTime>) {
+    if !is_package_root && dir.join("Cargo.toml").is_file() {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if file_type.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                newest_in(&entry.path(), false, newest);
+            }
+        } else {
+            newer(&entry.path(), newest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+    use std::time::Duration;
+
+    /// Sets a file's modification time.
+    fn touch(path: &Path, time: SystemTime) {
+        let file = fs::File::options().write(true).open(path).expect("open fixture file");
+        file.set_modified(time).expect("set modification time");
+    }
+
+    #[test]
+    fn build_inputs_include_members_and_path_dependencies() {
+        let dir = scratch_dir("artifacts-inputs");
+        let root = dir.join("ws");
+        write_file(
+            &root.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[workspace]\nmembers = [\"member\"]\n\n[dependencies]\nmember = { path = \"member\" }\n",
+        );
+        write_file(&root.join("src/main.rs"), "fn main() {}\n");
+        write_file(&root.join("README.md"), "# app\n");
+        write_file(&root.join("Cargo.lock"), "version = 3\n");
+        write_file(
+            &root.join("member/Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"0.1.0\"\n\n[dependencies]\nshared = { path = \"../../shared\" }\n",
+        );
+        write_file(&root.join("member/src/lib.rs"), "\n");
+        write_file(&dir.join("shared/Cargo.toml"), "[package]\nname = \"shared\"\nversion = \"0.1.0\"\n");
+        write_file(&dir.join("shared/src/lib.rs"), "\n");
+        write_file(&dir.join("shared/NOTES.md"), "\n");
+
+        let info = crate::parse_cargo_toml(&root.join("Cargo.toml")).expect("fixture manifest parses");
+        let built = SystemTime::now() + Duration::from_secs(3600);
+        let later = built + Duration::from_secs(3600);
+        let newest = |info: &ProjectInfo| newest_build_input(info).expect("build inputs exist");
+        assert!(newest(&info) < built);
+
+        // Files that are not build inputs do not make the build stale
+        touch(&root.join("README.md"), later);
+        touch(&dir.join("shared/NOTES.md"), later);
+        assert!(newest(&info) < built);
+
+        // A path dependency of a member does, and so does the lock file
+        touch(&dir.join("shared/src/lib.rs"), later);
+        assert_eq!(newest(&info), later);
+        touch(&root.join("Cargo.lock"), later + Duration::from_secs(60));
+        assert_eq!(newest(&info), later + Duration::from_secs(60));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
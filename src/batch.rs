@@ -0,0 +1,225 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{cargo, util, ProjectInfo};
+
+/// How running a command on one project turned out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// Cargo exited successfully.
+    Passed,
+    /// Cargo exited with the given non-zero code.
+    Failed(i32),
+    /// Cargo could not be started, e.g. because the log file could not be created.
+    Error(String),
+}
+
+impl Status {
+    /// Returns the word shown in the result column.
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Passed => "pass",
+            Status::Failed(_) => "FAIL",
+            Status::Error(_) => "ERROR",
+        }
+    }
+}
+
+/// The result of running a command on one project.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /// How the command exited.
+    pub status: Status,
+    /// How long the command ran.
+    pub elapsed: Duration,
+    /// The log file holding the command's output.
+    pub log: PathBuf,
+}
+
+/// Runs `cargo <command> <args>` in every given project, `jobs` at a time.
+///
+/// The output of each run goes to its own log file in `log_dir`, named after the project's
+/// position and name, e.g. `003-my_project.log`. A progress line is printed as each run
+/// finishes, in whatever order they finish.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to run the command in.
+/// * `labels` - The name each project is shown under, in the same order.
+/// * `command` - The cargo subcommand, e.g. `test`.
+/// * `args` - Further arguments for cargo, e.g. `--check` for `fmt`.
+/// * `jobs` - How many projects to run at once; at least 1.
+/// * `log_dir` - The directory to write the log files to; it must exist.
+///
+/// # Returns
+///
+/// The outcome of each run, in the same order as `projects`.
+pub fn each(projects: &[&ProjectInfo], labels: &[String], command: &str, args: &[String], jobs: usize, log_dir: &Path) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Outcome>>> = Mutex::new(vec![None; projects.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, projects.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(info) = projects.get(index) else {
+                    break;
+                };
+                let log = log_dir.join(format!("{:03}-{}.log", index + 1, file_name(&info.name)));
+                let outcome = run_one(info, command, args, log);
+
+                let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                println!(
+                    "[{}/{}] {:<5} {} ({})",
+                    done,
+                    projects.len(),
+                    outcome.status.label(),
+                    labels[index],
+                    util::format_duration(outcome.elapsed)
+                );
+                outcomes.lock().expect("outcome lock poisoned")[index] = Some(outcome);
+            });
+        }
+    });
+
+    outcomes
+        .into_inner()
+        .expect("outcome lock poisoned")
+        .into_iter()
+        .map(|outcome| outcome.expect("every project is run"))
+        .collect()
+}
+
+/// Runs the command in one project, writing a header and cargo's output to the log file.
+fn run_one(info: &ProjectInfo, command: &str, args: &[String], log: PathBuf) -> Outcome {
+    let started = Instant::now();
+    let status = match File::create(&log) {
+        Ok(mut file) => {
+            let command_line: Vec<&str> = std::iter::once(command).chain(args.iter().map(String::as_str)).collect();
+            let header = format!("$ cargo {}\n# in {}\n\n", command_line.join(" "), info.path.display());
+            match file.write_all(header.as_bytes()).map_err(|e| e.to_string()) {
+                Ok(()) => match cargo::cargo_logged(&info.path, command, args, &file) {
+                    Ok(status) if status.success() => Status::Passed,
+                    Ok(status) => Status::Failed(cargo::exit_code(status)),
+                    Err(message) => Status::Error(message),
+                },
+                Err(message) => Status::Error(format!("Could not write {}: {}", log.display(), message)),
+            }
+        }
+        Err(e) => Status::Error(format!("Could not create {}: {}", log.display(), e)),
+    };
+    Outcome { status, elapsed: started.elapsed(), log }
+}
+
+/// Turns a package name into a safe file name by replacing unusual characters with `_`.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Returns the directory a batch run writes its logs to, and creates it.
+///
+/// The logs go below the cache directory, e.g. `~/.cache/my_rust/logs/test-2024-05-01T134509Z`,
+/// so earlier runs stay available for comparison.
+///
+/// # Arguments
+///
+/// * `explicit` - A directory given with `--log-dir`, used instead of the default.
+/// * `command` - The cargo subcommand, used in the default directory name.
+pub fn log_dir(explicit: Option<&Path>, command: &str) -> Result<PathBuf, String> {
+    let dir = match explicit {
+        Some(dir) => dir.to_path_buf(),
+        None => {
+            let stamp = util::format_timestamp(std::time::SystemTime::now()).replace(':', "");
+            dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("my_rust")
+                .join("logs")
+                .join(format!("{}-{}", command, stamp))
+        }
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create the log directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Prints the pass/fail/time table of a batch run, followed by a count of the results.
+///
+/// # Arguments
+///
+/// * `labels` - The name each project is shown under.
+/// * `outcomes` - The outcome of each project, in the same order.
+pub fn print_summary(labels: &[String], outcomes: &[Outcome]) {
+    let name_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0).max("Project".len());
+    println!();
+    println!("{:<name_width$}  {:<8} {:>8}  Log", "Project", "Result", "Time");
+    for (label, outcome) in labels.iter().zip(outcomes) {
+        let result = match &outcome.status {
+            Status::Failed(code) => format!("{} {}", outcome.status.label(), code),
+            status => status.label().to_string(),
+        };
+        println!(
+            "{:<name_width$}  {:<8} {:>8}  {}",
+            label,
+            result,
+            util::format_duration(outcome.elapsed),
+            outcome.log.display()
+        );
+        if let Status::Error(message) = &outcome.status {
+            println!("{:<name_width$}  {}", "", message);
+        }
+    }
+
+    let passed = outcomes.iter().filter(|o| o.status == Status::Passed).count();
+    let total: Duration = outcomes.iter().map(|o| o.elapsed).sum();
+    println!();
+    println!(
+        "{} passed, {} failed, {} total ({} of work)",
+        passed,
+        outcomes.len() - passed,
+        outcomes.len(),
+        util::format_duration(total)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    #[test]
+    fn outcomes_keep_the_project_order() {
+        let dir = scratch_dir("batch-each");
+        let logs = dir.join("logs");
+        fs::create_dir_all(&logs).expect("create log directory");
+        let project = |name: &str, manifest: &str| {
+            let path = dir.join(name);
+            write_file(&path.join("Cargo.toml"), manifest);
+            write_file(&path.join("src/lib.rs"), "\n");
+            ProjectInfo { name: name.into(), path, ..ProjectInfo::default() }
+        };
+        let infos = [
+            project("good", "[package]\nname = \"good\"\nversion = \"0.1.0\"\n"),
+            project("broken", "[package\n"),
+            project("my.crate", "[package]\nname = \"other\"\nversion = \"0.1.0\"\n"),
+        ];
+        let projects: Vec<&ProjectInfo> = infos.iter().collect();
+        let labels: Vec<String> = infos.iter().map(|info| info.name.clone()).collect();
+
+        let outcomes = each(&projects, &labels, "verify-project", &["--quiet".to_string()], 3, &logs);
+        let statuses: Vec<bool> = outcomes.iter().map(|outcome| outcome.status == Status::Passed).collect();
+        assert_eq!(statuses, [true, false, true]);
+        assert!(matches!(outcomes[1].status, Status::Failed(_)));
+        let names: Vec<String> = outcomes.iter().map(|o| o.log.file_name().unwrap_or_default().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["001-good.log", "002-broken.log", "003-my_crate.log"]);
+        let log = fs::read_to_string(&outcomes[1].log).expect("log was written");
+        assert!(log.starts_with("$ cargo verify-project --quiet\n"), "{}", log);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::Table;
+use toml::Value;
+
+use crate::targets::{Target, TargetKind};
+use crate::output::kind_name;
+use crate::{deps, output, util, workspace, ProjectInfo, ProjectKind};
+
+/// The version of the cache file layout; a file written with another version is ignored.
+const FORMAT_VERSION: i64 = 3;
+
+/// The directories below a package directory whose entries decide which targets it has.
+const TARGET_DIRS: &[&str] = &["src", "src/bin", "examples", "tests", "benches"];
+
+/// The parsed manifests from earlier runs, stored in `index.toml` below the cache directory.
+///
+/// Each entry is keyed by the path of a `Cargo.toml` and remembers the files and directories
+/// its `ProjectInfo` was read from, with their modification times. An entry is reused while
+/// none of them changed; when only the manifest's time changed but its content did not, the
+/// entry is reused as well and just gets the new time.
+///
+/// The cache can be shared by the threads of a parallel walk.
+#[derive(Debug, Default)]
+pub struct Cache {
+    /// The cache file.
+    path: PathBuf,
+    /// The entries, keyed by manifest path.
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+    /// Whether to parse every manifest again instead of reusing entries (`--refresh`).
+    refresh: bool,
+    /// Whether an entry was added or updated since the cache was loaded.
+    changed: AtomicBool,
+}
+
+/// What the cache remembers about one manifest.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// The FNV-1a hash of the manifest's content.
+    hash: u64,
+    /// The files and directories the project was read from, with their modification times.
+    inputs: Vec<(PathBuf, Option<i64>)>,
+    /// The parsed project, or `None` if the manifest did not describe one.
+    info: Option<ProjectInfo>,
+}
+
+/// How the entries of the cache compare with the disk, as reported by `index stats`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// The cache file.
+    pub path: PathBuf,
+    /// The size of the cache file in bytes, or `None` if it does not exist yet.
+    pub size: Option<u64>,
+    /// When the cache file was last written.
+    pub written: Option<SystemTime>,
+    /// How many manifests the cache holds.
+    pub entries: usize,
+    /// How many of them describe a project.
+    pub projects: usize,
+    /// Entries that would be reused as they are.
+    pub fresh: usize,
+    /// Entries whose manifest or another input changed since they were stored.
+    pub outdated: usize,
+    /// Entries whose manifest no longer exists.
+    pub missing: usize,
+}
+
+impl Cache {
+    /// Loads the cache file, or starts an empty cache if it is missing, unreadable or was
+    /// written by an incompatible version.
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh` - Parse every manifest again; the entries of manifests that are not
+    ///   visited are still kept.
+    pub fn load(refresh: bool) -> Cache {
+        let path = cache_file();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| text.parse::<Value>().ok())
+            .filter(|document| document.get("version").and_then(Value::as_integer) == Some(FORMAT_VERSION))
+            .and_then(|document| document.get("entry")?.as_array().cloned())
+            .map(|entries| entries.iter().filter_map(entry_from_value).collect())
+            .unwrap_or_default();
+        Cache { path, entries: Mutex::new(entries), refresh, changed: AtomicBool::new(false) }
+    }
+
+    /// Returns an empty cache that is saved to the given file, for tests.
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Cache {
+        Cache { path, ..Cache::default() }
+    }
+
+    /// Locks the entries.
+    fn entries(&self) -> MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries.lock().expect("index cache lock poisoned")
+    }
+
+    /// Returns the project described by a manifest, from the cache if it is up to date.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - The path of the `Cargo.toml`.
+    /// * `parse` - Parses the manifest when the cache has no usable entry for it.
+    pub fn project(&self, manifest: &Path, parse: impl FnOnce(&Path) -> Option<ProjectInfo>) -> Option<ProjectInfo> {
+        let cached = if self.refresh { None } else { self.entries().get(manifest).cloned() };
+        if let Some(mut entry) = cached {
+            let mtime = modified(manifest);
+            if is_reusable(manifest, &entry, mtime) {
+                let info = entry.info.clone();
+                if entry.inputs.iter().any(|(path, recorded)| path == manifest && *recorded != mtime) {
+                    for (path, recorded) in &mut entry.inputs {
+                        if path == manifest {
+                            *recorded = mtime;
+                        }
+                    }
+                    self.entries().insert(manifest.to_path_buf(), entry);
+                    self.changed.store(true, Ordering::Relaxed);
+                }
+                return info;
+            }
+        }
+
+        let hash = fs::read(manifest).map(|content| fnv1a(&content)).unwrap_or(0);
+        let info = parse(manifest);
+        let inputs = inputs(manifest, info.as_ref()).into_iter().map(|path| {
+            let mtime = modified(&path);
+            (path, mtime)
+        });
+        let entry = Entry { hash, inputs: inputs.collect(), info: info.clone() };
+        self.entries().insert(manifest.to_path_buf(), entry);
+        self.changed.store(true, Ordering::Relaxed);
+        info
+    }
+
+    /// Writes the cache file if anything changed, dropping the entries of deleted manifests.
+    pub fn save(&mut self) -> Result<(), String> {
+        if !*self.changed.get_mut() {
+            return Ok(());
+        }
+        let entries = self.entries.get_mut().expect("index cache lock poisoned");
+        entries.retain(|manifest, _| manifest.is_file());
+        let mut manifests: Vec<&PathBuf> = entries.keys().collect();
+        manifests.sort();
+
+        let mut document = Table::new();
+        document.insert("version".into(), Value::Integer(FORMAT_VERSION));
+        document.insert("written-by".into(), Value::String(env!("CARGO_PKG_VERSION").into()));
+        let entries = manifests.into_iter().map(|manifest| entry_value(manifest, &entries[manifest])).collect();
+        document.insert("entry".into(), Value::Array(entries));
+        let text = toml::to_string(&Value::Table(document)).map_err(|e| e.to_string())?;
+
+        // Write to a temporary file first so a concurrent run never reads half a cache
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        let temporary = self.path.with_extension(format!("toml.{}", std::process::id()));
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&temporary, text))
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temporary);
+                format!("Could not write the index cache {}: {}", self.path.display(), e)
+            })?;
+        *self.changed.get_mut() = false;
+        Ok(())
+    }
+
+    /// Compares every entry with the disk without parsing anything.
+    pub fn stats(&self) -> Stats {
+        let entries = self.entries();
+        let metadata = fs::metadata(&self.path).ok();
+        let mut stats = Stats {
+            path: self.path.clone(),
+            size: metadata.as_ref().map(|m| m.len()),
+            written: metadata.and_then(|m| m.modified().ok()),
+            entries: entries.len(),
+            projects: entries.values().filter(|entry| entry.info.is_some()).count(),
+            ..Stats::default()
+        };
+        for (manifest, entry) in entries.iter() {
+            if !manifest.is_file() {
+                stats.missing += 1;
+            } else if entry.inputs.iter().all(|(path, mtime)| modified(path) == *mtime) {
+                stats.fresh += 1;
+            } else {
+                stats.outdated += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// Returns `true` if an entry can be used instead of parsing its manifest again.
+///
+/// Every input other than the manifest must be unchanged; the manifest itself must either
+/// have the recorded time or, failing that, the recorded content.
+fn is_reusable(manifest: &Path, entry: &Entry, mtime: Option<i64>) -> bool {
+    let (own, others): (Vec<_>, Vec<_>) = entry.inputs.iter().partition(|(path, _)| path == manifest);
+    others.iter().all(|(path, recorded)| modified(path) == *recorded)
+        && (own.iter().all(|(_, recorded)| *recorded == mtime) || fs::read(manifest).is_ok_and(|content| fnv1a(&content) == entry.hash))
+}
+
+/// Returns the path of the cache file, `index.toml` in the `my_rust` cache directory.
+fn cache_file() -> PathBuf {
+    dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("my_rust").join("index.toml")
+}
+
+/// Lists the files and directories a project was read from.
+///
+/// Besides the manifest these are the manifests of workspace members, the workspace root
+/// manifest fields or dependencies were inherited from, and the directories whose entries decide which
+/// members and targets exist, so that adding a binary or a member invalidates the entry. The
+/// paths `members` and `exclude` expand from are recorded even when they match nothing yet.
+fn inputs(manifest: &Path, info: Option<&ProjectInfo>) -> Vec<PathBuf> {
+    let mut inputs = vec![manifest.to_path_buf()];
+    let Some(info) = info else {
+        return inputs;
+    };
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    if info.is_workspace() {
+        let document = fs::read_to_string(manifest).ok().and_then(|text| text.parse::<Value>().ok());
+        if let Some(table) = document.as_ref().and_then(|document| document.get("workspace")) {
+            inputs.extend(workspace::member_inputs(table, dir));
+        }
+    }
+    if !info.inherited.is_empty() || info.dependencies.iter().any(|d| d.inherited) {
+        if let Some(root) = workspace::enclosing_root(dir) {
+            inputs.push(root.join("Cargo.toml"));
+        }
+    }
+    let packages = std::iter::once(dir.to_path_buf()).chain(info.members.iter().map(|member| member.path.clone()));
+    for package in packages {
+        if package != dir {
+            inputs.push(package.join("Cargo.toml"));
+            if let Some(parent) = package.parent() {
+                inputs.push(parent.to_path_buf());
+            }
+        }
+        inputs.push(package.clone());
+        for target_dir in TARGET_DIRS {
+            let target_dir = package.join(target_dir);
+            if let Ok(entries) = fs::read_dir(&target_dir) {
+                let subdirs = entries.filter_map(Result::ok).filter(|e| e.file_type().is_ok_and(|t| t.is_dir()));
+                inputs.extend(subdirs.map(|e| e.path()));
+            }
+            inputs.push(target_dir);
+        }
+    }
+    inputs.sort();
+    inputs.dedup();
+    inputs
+}
+
+/// Returns the modification time of a path in nanoseconds since the epoch, or `None` if it
+/// does not exist.
+fn modified(path: &Path) -> Option<i64> {
+    let time = fs::metadata(path).ok()?.modified().ok()?;
+    let nanos = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_nanos() as i64,
+        Err(before) => -(before.duration().as_nanos() as i64),
+    };
+    Some(nanos)
+}
+
+/// Hashes bytes with 64-bit FNV-1a, which is plenty to notice that a manifest was edited.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Turns an entry into a `[[entry]]` table of the cache file.
+fn entry_value(manifest: &Path, entry: &Entry) -> Value {
+    let mut table = Table::new();
+    table.insert("manifest".into(), path_value(manifest));
+    table.insert("hash".into(), Value::String(format!("{:016x}", entry.hash)));
+    // The missing inputs are recorded too, with a time of -1, so that creating them invalidates the entry
+    table.insert("inputs".into(), Value::Array(entry.inputs.iter().map(|(path, _)| path_value(path)).collect()));
+    table.insert("mtimes".into(), Value::Array(entry.inputs.iter().map(|(_, mtime)| Value::Integer(mtime.unwrap_or(-1))).collect()));
+    if let Some(info) = &entry.info {
+        table.insert("project".into(), info_value(info));
+    }
+    Value::Table(table)
+}
+
+/// Reads an `[[entry]]` table of the cache file, or `None` if it is malformed.
+fn entry_from_value(value: &Value) -> Option<(PathBuf, Entry)> {
+    let manifest = PathBuf::from(value.get("manifest")?.as_str()?);
+    let hash = u64::from_str_radix(value.get("hash")?.as_str()?, 16).ok()?;
+    let paths = value.get("inputs")?.as_array()?.iter().map(|p| p.as_str().map(PathBuf::from));
+    let mtimes = value.get("mtimes")?.as_array()?.iter().map(|m| m.as_integer().map(|m| (m != -1).then_some(m)));
+    let inputs = paths.zip(mtimes).map(|(path, mtime)| Some((path?, mtime?))).collect::<Option<Vec<_>>>()?;
+    let info = match value.get("project") {
+        Some(project) => Some(info_from_value(project)?),
+        None => None,
+    };
+    Some((manifest, Entry { hash, inputs, info }))
+}
+
+/// Turns a path into a TOML string.
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into_owned())
+}
+
+/// Turns a list of strings into a TOML array.
+fn string_array(items: &[String]) -> Value {
+    Value::Array(items.iter().cloned().map(Value::String).collect())
+}
+
+/// Turns a parsed project into a TOML table, leaving out the scan root, which is set anew
+/// on every run.
+fn info_value(info: &ProjectInfo) -> Value {
+    let mut table = Table::new();
+    table.insert("name".into(), Value::String(info.name.clone()));
+    let optional = [
+        ("description", &info.description),
+        ("version", &info.version),
+        ("license", &info.license),
+        ("license-file", &info.license_file),
+        ("edition", &info.edition),
+        ("rust-version", &info.rust_version),
+        ("repository", &info.repository),
+        ("homepage", &info.homepage),
+        ("documentation", &info.documentation),
+        ("readme", &info.readme),
+        ("default-run", &info.default_run),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            table.insert(key.into(), Value::String(value.clone()));
+        }
+    }
+    table.insert("authors".into(), string_array(&info.authors));
+    table.insert("keywords".into(), string_array(&info.keywords));
+    table.insert("categories".into(), string_array(&info.categories));
+    if let Some(publish) = &info.publish {
+        table.insert("publish".into(), string_array(publish));
+    }
+    table.insert("path".into(), path_value(&info.path));
+    table.insert("kind".into(), Value::String(kind_name(info.kind).into()));
+    table.insert("inherited".into(), string_array(&info.inherited));
+    let targets = info.targets.iter().map(|target| {
+        let mut t = Table::new();
+        t.insert("kind".into(), Value::String(target.kind.to_string()));
+        t.insert("name".into(), Value::String(target.name.clone()));
+        t.insert("path".into(), path_value(&target.path));
+        t.insert("declared".into(), Value::Boolean(target.declared));
+        Value::Table(t)
+    });
+    table.insert("targets".into(), Value::Array(targets.collect()));
+    table.insert("dependencies".into(), Value::Array(info.dependencies.iter().map(deps::record).collect()));
+    table.insert("members".into(), Value::Array(info.members.iter().map(info_value).collect()));
+    Value::Table(table)
+}
+
+/// Reads a project table written by `info_value`, or `None` if it is malformed.
+fn info_from_value(value: &Value) -> Option<ProjectInfo> {
+    let string = |key: &str| value.get(key).and_then(Value::as_str).map(String::from);
+    let strings = |key: &str| -> Option<Vec<String>> {
+        value.get(key)?.as_array()?.iter().map(|item| item.as_str().map(String::from)).collect()
+    };
+    let targets = value
+        .get("targets")?
+        .as_array()?
+        .iter()
+        .map(|target| {
+            Some(Target {
+                kind: target_kind(target.get("kind")?.as_str()?)?,
+                name: target.get("name")?.as_str()?.to_string(),
+                path: PathBuf::from(target.get("path")?.as_str()?),
+                declared: target.get("declared")?.as_bool()?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let dependencies = value.get("dependencies")?.as_array()?.iter().map(deps::from_record).collect::<Option<Vec<_>>>()?;
+    let members = value.get("members")?.as_array()?.iter().map(info_from_value).collect::<Option<Vec<_>>>()?;
+    let publish = match value.get("publish") {
+        Some(_) => Some(strings("publish")?),
+        None => None,
+    };
+
+    Some(ProjectInfo {
+        name: string("name")?,
+        description: string("description"),
+        version: string("version"),
+        authors: strings("authors")?,
+        license: string("license"),
+        license_file: string("license-file"),
+        edition: string("edition"),
+        rust_version: string("rust-version"),
+        repository: string("repository"),
+        homepage: string("homepage"),
+        documentation: string("documentation"),
+        readme: string("readme"),
+        keywords: strings("keywords")?,
+        categories: strings("categories")?,
+        publish,
+        default_run: string("default-run"),
+        targets,
+        dependencies,
+        path: PathBuf::from(string("path")?),
+        root: PathBuf::new(),
+        kind: project_kind(&string("kind")?)?,
+        members,
+        inherited: strings("inherited")?,
+    })
+}
+
+/// Reads a project kind stored by `kind_name`.
+fn project_kind(name: &str) -> Option<ProjectKind> {
+    [ProjectKind::Package, ProjectKind::Workspace, ProjectKind::VirtualWorkspace]
+        .into_iter()
+        .find(|kind| kind_name(*kind) == name)
+}
+
+/// Reads a target kind stored by its `Display` name, e.g. `bin`.
+fn target_kind(name: &str) -> Option<TargetKind> {
+    [TargetKind::Lib, TargetKind::Bin, TargetKind::Example, TargetKind::Test, TargetKind::Bench]
+        .into_iter()
+        .find(|kind| kind.to_string() == name)
+}
+
+/// Prints the `index stats` report.
+pub fn print_stats(stats: &Stats) {
+    println!("Cache file: {}", stats.path.display());
+    match (stats.size, stats.written) {
+        (Some(size), Some(written)) => println!(
+            "Size:       {}, written {} ({})",
+            util::format_size(size),
+            util::format_time(written),
+            util::format_age(written)
+        ),
+        _ => println!("Size:       not written yet"),
+    }
+    println!("Manifests:  {} ({} projects)", stats.entries, stats.projects);
+    println!("Up to date: {}", stats.fresh);
+    println!("Outdated:   {}", stats.outdated);
+    println!("Missing:    {}", stats.missing);
+}
+
+/// Turns the `index stats` report into a record for the machine-readable formats.
+pub fn stats_record(stats: &Stats) -> Value {
+    let mut table = Table::new();
+    table.insert("path".into(), path_value(&stats.path));
+    if let Some(size) = stats.size {
+        table.insert("size".into(), Value::Integer(size as i64));
+    }
+    if let Some(written) = stats.written {
+        table.insert("written".into(), output::time_value(written));
+    }
+    for (key, count) in [("manifests", stats.entries), ("projects", stats.projects), ("up-to-date", stats.fresh), ("outdated", stats.outdated), ("missing", stats.missing)] {
+        table.insert(key.into(), Value::Integer(count as i64));
+    }
+    Value::Table(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    /// Returns a cache that is never saved, holding one freshly parsed entry for `manifest`.
+    fn cached(manifest: &Path) -> (Cache, Entry) {
+        let cache = Cache { path: manifest.with_extension("cache"), ..Cache::default() };
+        cache.project(manifest, crate::parse_cargo_toml);
+        let entry = cache.entries().get(manifest).cloned().expect("entry was stored");
+        (cache, entry)
+    }
+
+    #[test]
+    fn unchanged_entries_are_reused() {
+        let dir = scratch_dir("cache-unchanged");
+        let manifest = dir.join("Cargo.toml");
+        write_file(&manifest, "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        write_file(&dir.join("src/main.rs"), "fn main() {}\n");
+        let (_cache, entry) = cached(&manifest);
+        assert!(is_reusable(&manifest, &entry, modified(&manifest)));
+
+        // Rewriting the same content keeps the entry, a binary being added does not
+        write_file(&manifest, "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        assert!(is_reusable(&manifest, &entry, modified(&manifest)));
+        write_file(&dir.join("src/bin/tool.rs"), "fn main() {}\n");
+        assert!(!is_reusable(&manifest, &entry, modified(&manifest)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn members_matching_nothing_yet_invalidate_the_entry() {
+        let dir = scratch_dir("cache-new-members");
+        let manifest = dir.join("Cargo.toml");
+        write_file(&manifest, "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\n");
+        let (_cache, entry) = cached(&manifest);
+        assert!(entry.info.as_ref().is_some_and(|info| info.members.is_empty()));
+        assert!(entry.inputs.iter().any(|(path, _)| *path == dir.join("crates")));
+        assert!(entry.inputs.iter().any(|(path, mtime)| *path == dir.join("tools/cli") && mtime.is_none()));
+
+        // The glob's directory appearing
+        fs::create_dir(dir.join("crates")).expect("create crates");
+        assert!(!is_reusable(&manifest, &entry, modified(&manifest)));
+        let (_cache, entry) = cached(&manifest);
+        write_file(&dir.join("crates/a/Cargo.toml"), "[package]\nname = \"a\"\nversion = \"0.1.0\"\n");
+        assert!(!is_reusable(&manifest, &entry, modified(&manifest)));
+        let (_cache, entry) = cached(&manifest);
+        assert_eq!(entry.info.as_ref().map(|info| info.members.len()), Some(1));
+
+        // An explicit member path that did not exist
+        write_file(&dir.join("tools/cli/Cargo.toml"), "[package]\nname = \"cli\"\nversion = \"0.1.0\"\n");
+        assert!(!is_reusable(&manifest, &entry, modified(&manifest)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_manifest_in_an_existing_member_directory_invalidates_the_entry() {
+        let dir = scratch_dir("cache-member-manifest");
+        let manifest = dir.join("Cargo.toml");
+        write_file(&manifest, "[workspace]\nmembers = [\"crates/*\"]\n");
+        fs::create_dir_all(dir.join("crates/b")).expect("create member directory");
+        let (_cache, entry) = cached(&manifest);
+        write_file(&dir.join("crates/b/Cargo.toml"), "[package]\nname = \"b\"\nversion = \"0.1.0\"\n");
+        assert!(!is_reusable(&manifest, &entry, modified(&manifest)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The Cargo subcommands that can be run on a project, with a short description of each.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("build", "Compiles the project"),
+    ("run", "Builds and runs a binary of the project"),
+    ("test", "Runs the tests of the project"),
+    ("check", "Checks the project for errors without building it"),
+    ("clippy", "Runs Clippy on the project"),
+    ("doc", "Builds the documentation of the project"),
+];
+
+/// The number of child processes currently running, so Ctrl+C is left to them.
+static CHILDREN_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns `true` if the given name is one of the `COMMANDS`.
+pub fn is_command(name: &str) -> bool {
+    COMMANDS.iter().any(|(command, _)| *command == name)
+}
+
+/// Returns `true` while a child process started by `run` or `cargo_logged` is running.
+///
+/// The Ctrl+C handler uses this to let the child deal with the interrupt, so its exit code
+/// is still forwarded.
+pub fn child_running() -> bool {
+    CHILDREN_RUNNING.load(Ordering::SeqCst) > 0
+}
+
+/// Runs a program in a directory with the terminal's stdin, stdout and stderr.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to run the program in.
+/// * `program` - The program to run, e.g. `cargo`.
+/// * `args` - The arguments to pass to the program.
+///
+/// # Returns
+///
+/// The exit status of the program, or an error message if it could not be started.
+pub fn run(dir: &Path, program: &str, args: &[String]) -> Result<ExitStatus, String> {
+    wait_for(Command::new(program).args(args).current_dir(dir), program)
+}
+
+/// Runs a command while counting it in `CHILDREN_RUNNING`, and waits for it to exit.
+fn wait_for(command: &mut Command, program: &str) -> Result<ExitStatus, String> {
+    CHILDREN_RUNNING.fetch_add(1, Ordering::SeqCst);
+    let status = command.status();
+    CHILDREN_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    status.map_err(|e| format!("Could not run `{}`: {}", program, e))
+}
+
+/// Runs `cargo <subcommand> <args>` in a project directory.
+///
+/// # Arguments
+///
+/// * `dir` - The project directory.
+/// * `subcommand` - The Cargo subcommand, e.g. `build`.
+/// * `args` - Further arguments for Cargo; arguments after a `--` among them reach the program or test harness.
+///
+/// # Returns
+///
+/// The exit status of Cargo, or an error message if it could not be started.
+pub fn cargo(dir: &Path, subcommand: &str, args: &[String]) -> Result<ExitStatus, String> {
+    let mut all = vec![subcommand.to_string()];
+    all.extend(args.iter().cloned());
+    run(dir, "cargo", &all)
+}
+
+/// Runs `cargo <subcommand> <args>` in a project directory with its output going to a log file.
+///
+/// Stdout and stderr both go to the log, and stdin is closed so cargo never waits for input.
+///
+/// # Arguments
+///
+/// * `dir` - The project directory.
+/// * `subcommand` - The Cargo subcommand, e.g. `test`.
+/// * `args` - Further arguments for Cargo.
+/// * `log` - The open log file; cargo's output is appended to what it already holds.
+///
+/// # Returns
+///
+/// The exit status of Cargo, or an error message if it could not be started.
+pub fn cargo_logged(dir: &Path, subcommand: &str, args: &[String], log: &File) -> Result<ExitStatus, String> {
+    let stdout = log.try_clone().map_err(|e| format!("Could not write the log file: {}", e))?;
+    let stderr = log.try_clone().map_err(|e| format!("Could not write the log file: {}", e))?;
+    let mut command = Command::new("cargo");
+    command
+        .arg(subcommand)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr);
+    wait_for(&mut command, "cargo")
+}
+
+/// Converts an exit status into the code this tool should exit with.
+///
+/// A program killed by a signal is reported as `128 + signal`, like a shell does.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    1
+}
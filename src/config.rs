@@ -0,0 +1,272 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Where the value of a configuration setting came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The built-in default.
+    Default,
+    /// A configuration file.
+    File(PathBuf),
+    /// An environment variable.
+    Environment(&'static str),
+    /// A command-line option.
+    CommandLine(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Environment(var) => write!(f, "environment (${})", var),
+            Source::CommandLine(flag) => write!(f, "command line ({})", flag),
+        }
+    }
+}
+
+/// A configuration value together with the place it was set.
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    /// The effective value.
+    pub value: T,
+    /// Where the value came from.
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    /// Creates a setting holding its built-in default.
+    fn default(value: T) -> Self {
+        Setting { value, source: Source::Default }
+    }
+
+    /// Replaces the value, recording where the new value came from.
+    pub fn set(&mut self, value: T, source: Source) {
+        self.value = value;
+        self.source = source;
+    }
+}
+
+/// The effective configuration, merged from the defaults, the config file and the command line.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The directories that are searched for projects.
+    pub roots: Setting<Vec<PathBuf>>,
+    /// Glob patterns for directories that are never searched.
+    pub exclude: Setting<Vec<String>>,
+    /// The default sort order of the project list.
+    pub sort: Setting<String>,
+    /// The default output format.
+    pub format: Setting<String>,
+    /// The default maximum search depth, or `None` for no limit.
+    pub max_depth: Setting<Option<usize>>,
+    /// The configuration file that was loaded, if any.
+    pub file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let home_dir = dirs::home_dir().expect("Could not find home directory");
+        Config {
+            roots: Setting::default(vec![home_dir.join("rust")]),
+            exclude: Setting::default(Vec::new()),
+            sort: Setting::default("name".to_string()),
+            format: Setting::default("text".to_string()),
+            max_depth: Setting::default(None),
+            file: None,
+        }
+    }
+}
+
+/// Returns the default location of the configuration file.
+///
+/// This is `config.toml` in the `my_rust` directory of the platform's configuration directory,
+/// which honours `$XDG_CONFIG_HOME` on Linux (usually `~/.config/my_rust/config.toml`).
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("my_rust").join("config.toml"))
+}
+
+/// Loads the effective configuration.
+///
+/// The built-in defaults are overlaid with the configuration file. An explicitly given file
+/// must exist; the default file is optional.
+///
+/// # Arguments
+///
+/// * `explicit` - The file passed with `--config`, if any.
+///
+/// # Returns
+///
+/// The merged configuration, or a message describing why the file could not be used.
+pub fn load(explicit: Option<&Path>) -> Result<Config, String> {
+    let mut config = Config::default();
+
+    let path = match explicit {
+        Some(path) => path.to_path_buf(),
+        None => match default_path() {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(config),
+        },
+    };
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read config file {}: {}", path.display(), e))?;
+    let table: Value = contents
+        .parse()
+        .map_err(|e| format!("Could not parse config file {}: {}", path.display(), e))?;
+    apply_file(&mut config, &table, &path)?;
+    config.file = Some(path);
+    Ok(config)
+}
+
+/// The environment variable holding the scan roots, separated like `$PATH`.
+pub const ROOTS_VAR: &str = "MY_RUST_ROOTS";
+
+/// Overlays the scan roots from the `MY_RUST_ROOTS` environment variable.
+///
+/// The variable uses the platform's path separator (`:` on Unix), and empty entries are
+/// ignored. An unset or empty variable leaves the configuration unchanged.
+pub fn apply_env(config: &mut Config) {
+    let Some(value) = std::env::var_os(ROOTS_VAR) else {
+        return;
+    };
+    let roots: Vec<PathBuf> = std::env::split_paths(&value)
+        .filter(|root| !root.as_os_str().is_empty())
+        .map(|root| expand_home(&root.to_string_lossy()))
+        .collect();
+    if !roots.is_empty() {
+        config.roots.set(roots, Source::Environment(ROOTS_VAR));
+    }
+}
+
+/// Overlays the values of a parsed configuration file onto the configuration.
+fn apply_file(config: &mut Config, table: &Value, path: &Path) -> Result<(), String> {
+    let source = Source::File(path.to_path_buf());
+    let invalid = |key: &str, expected: &str| format!("{}: `{}` must be {}", path.display(), key, expected);
+
+    if let Some(value) = table.get("roots") {
+        let roots = string_array(value).ok_or_else(|| invalid("roots", "an array of strings"))?;
+        config.roots.set(roots.iter().map(|r| expand_home(r)).collect(), source.clone());
+    }
+    if let Some(value) = table.get("exclude") {
+        let exclude = string_array(value).ok_or_else(|| invalid("exclude", "an array of strings"))?;
+        config.exclude.set(exclude, source.clone());
+    }
+    if let Some(value) = table.get("sort") {
+        let sort = value.as_str().ok_or_else(|| invalid("sort", "a string"))?;
+        config.sort.set(sort.to_string(), source.clone());
+    }
+    if let Some(value) = table.get("format") {
+        let format = value.as_str().ok_or_else(|| invalid("format", "a string"))?;
+        config.format.set(format.to_string(), source.clone());
+    }
+    if let Some(value) = table.get("max-depth") {
+        let depth = value
+            .as_integer()
+            .and_then(|d| usize::try_from(d).ok())
+            .ok_or_else(|| invalid("max-depth", "a non-negative integer"))?;
+        config.max_depth.set(Some(depth), source);
+    }
+    Ok(())
+}
+
+/// Reads a TOML array of strings, returning `None` if any element is not a string.
+fn string_array(value: &Value) -> Option<Vec<String>> {
+    value.as_array()?.iter().map(|v| v.as_str().map(String::from)).collect()
+}
+
+/// Expands a leading `~` in a path to the user's home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Prints the effective configuration and the source of every value.
+///
+/// The output is written in the same TOML layout as the configuration file, with the
+/// source of each value as a trailing comment.
+pub fn show(config: &Config) {
+    match &config.file {
+        Some(path) => println!("# Configuration file: {}", path.display()),
+        None => println!("# Configuration file: none (looked for {})", display_default_path()),
+    }
+
+    let roots = config.roots.value.iter().map(|r| Value::String(r.display().to_string())).collect();
+    print_setting("roots", Value::Array(roots), &config.roots.source);
+    let exclude = config.exclude.value.iter().cloned().map(Value::String).collect();
+    print_setting("exclude", Value::Array(exclude), &config.exclude.source);
+    print_setting("sort", Value::String(config.sort.value.clone()), &config.sort.source);
+    print_setting("format", Value::String(config.format.value.clone()), &config.format.source);
+    match config.max_depth.value {
+        Some(depth) => print_setting("max-depth", Value::Integer(depth as i64), &config.max_depth.source),
+        None => println!("# max-depth = unlimited  # {}", config.max_depth.source),
+    }
+}
+
+/// Prints a single `key = value` line followed by the value's source.
+fn print_setting(key: &str, value: Value, source: &Source) {
+    println!("{}  # {}", setting_line(key, value), source);
+}
+
+/// Writes a `key = value` line the way the configuration file spells it, so that it can be
+/// pasted back into the file.
+fn setting_line(key: &str, value: Value) -> String {
+    let mut table = toml::value::Table::new();
+    table.insert(key.to_string(), value);
+    let text = toml::to_string(&Value::Table(table)).expect("Failed to serialise TOML");
+    text.trim_end().to_string()
+}
+
+/// Returns the default configuration path for display.
+fn display_default_path() -> String {
+    default_path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "no configuration directory".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    #[test]
+    fn the_file_overlays_the_defaults() {
+        let dir = scratch_dir("config-merge");
+        let path = dir.join("config.toml");
+        write_file(&path, "roots = [\"~/src\", \"/work\"]\nsort = \"size\"\nmax-depth = 3\n");
+        let config = load(Some(&path)).expect("valid config file");
+        let home = dirs::home_dir().expect("home directory");
+        assert_eq!(config.roots.value, [home.join("src"), PathBuf::from("/work")]);
+        assert_eq!(config.roots.source, Source::File(path.clone()));
+        assert_eq!(config.sort.value, "size");
+        assert_eq!(config.max_depth.value, Some(3));
+        assert_eq!(config.format.value, "text");
+        assert_eq!(config.format.source, Source::Default);
+        assert!(config.exclude.value.is_empty());
+        assert_eq!(config.file, Some(path.clone()));
+
+        write_file(&path, "max-depth = -1\n");
+        let error = load(Some(&path)).expect_err("negative depth is rejected");
+        assert!(error.contains("`max-depth` must be a non-negative integer"), "{}", error);
+        write_file(&path, "exclude = [\"target\", 3]\n");
+        assert!(load(Some(&path)).is_err());
+        assert!(load(Some(&dir.join("missing.toml"))).is_err(), "an explicit file must exist");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn settings_are_shown_as_valid_toml() {
+        let roots = vec![Value::String("C:\\Users\\zoë\\code".into()), Value::String("say \"hi\"\ttab".into())];
+        let line = setting_line("roots", Value::Array(roots.clone()));
+        let parsed: Value = line.parse().unwrap_or_else(|e| panic!("{:?} is not valid TOML: {}", line, e));
+        assert_eq!(parsed.get("roots"), Some(&Value::Array(roots)));
+        assert_eq!(setting_line("max-depth", Value::Integer(3)), "max-depth = 3");
+        assert_eq!(setting_line("sort", Value::String("size".into())), "sort = \"size\"");
+    }
+}
@@ -0,0 +1,430 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use toml::value::Table;
+use toml::Value;
+
+use crate::workspace;
+
+/// The dependency tables of a manifest and the kind of dependency each one declares.
+///
+/// Cargo still accepts the old spellings with an underscore, so they are read as well.
+const TABLES: &[(&str, DependencyKind)] = &[
+    ("dependencies", DependencyKind::Normal),
+    ("dev-dependencies", DependencyKind::Dev),
+    ("dev_dependencies", DependencyKind::Dev),
+    ("build-dependencies", DependencyKind::Build),
+    ("build_dependencies", DependencyKind::Build),
+];
+
+/// Which builds a dependency is used in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+    /// A `[dependencies]` entry, used by the package itself.
+    Normal,
+    /// A `[dev-dependencies]` entry, used by tests, examples and benches.
+    Dev,
+    /// A `[build-dependencies]` entry, used by the build script.
+    Build,
+}
+
+impl DependencyKind {
+    /// Reads a kind written by its `Display` name, e.g. `dev`.
+    pub fn from_name(name: &str) -> Option<DependencyKind> {
+        [DependencyKind::Normal, DependencyKind::Dev, DependencyKind::Build]
+            .into_iter()
+            .find(|kind| kind.to_string() == name)
+    }
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Dev => "dev",
+            DependencyKind::Build => "build",
+        };
+        f.pad(name)
+    }
+}
+
+/// Where a dependency is fetched from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A registry: crates.io, or the named alternative registry.
+    Registry(Option<String>),
+    /// A Git repository, optionally pinned to a `branch`, `tag` or `rev`.
+    Git {
+        /// The repository URL.
+        url: String,
+        /// The `branch`, `tag` or `rev` key and its value, if one is given.
+        reference: Option<(String, String)>,
+    },
+    /// A local directory, resolved against the manifest that declared it.
+    Path(PathBuf),
+    /// A `{ workspace = true }` entry that no `[workspace.dependencies]` table defines.
+    Workspace,
+}
+
+impl Source {
+    /// The short name of the source written in the `source` field: `registry`, `git`, `path`
+    /// or `workspace`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Registry(_) => "registry",
+            Source::Git { .. } => "git",
+            Source::Path(_) => "path",
+            Source::Workspace => "workspace",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Registry(None) => write!(f, "registry"),
+            Source::Registry(Some(registry)) => write!(f, "registry {}", registry),
+            Source::Git { url, reference: None } => write!(f, "git {}", url),
+            Source::Git { url, reference: Some((key, value)) } => write!(f, "git {} ({} {})", url, key, value),
+            Source::Path(path) => write!(f, "path {}", path.display()),
+            Source::Workspace => write!(f, "workspace"),
+        }
+    }
+}
+
+/// A dependency declared in a package's manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The name the dependency is known by in the package, i.e. its key in the table.
+    pub name: String,
+    /// The real package name when the dependency is renamed with `package = "..."`.
+    pub package: Option<String>,
+    /// Which table the dependency is declared in.
+    pub kind: DependencyKind,
+    /// Where the dependency comes from.
+    pub source: Source,
+    /// The version requirement, if one is given.
+    pub req: Option<String>,
+    /// Whether the dependency is only built when a feature enables it.
+    pub optional: bool,
+    /// Whether the dependency's default features are enabled.
+    pub default_features: bool,
+    /// The features enabled on the dependency.
+    pub features: Vec<String>,
+    /// The platform of a `[target.'cfg(...)'.dependencies]` section, e.g. `cfg(unix)`.
+    pub target: Option<String>,
+    /// Whether the entry is `{ workspace = true }` and was filled in from the workspace's
+    /// `[workspace.dependencies]` table.
+    pub inherited: bool,
+}
+
+/// Reads every dependency declared in a manifest.
+///
+/// `[dependencies]`, `[dev-dependencies]` and `[build-dependencies]` are read both at the top
+/// level and in each `[target.<platform>]` table. Entries written as `{ workspace = true }`
+/// take their source, version requirement and default features from the workspace's
+/// `[workspace.dependencies]`, with their own features added to the workspace's.
+///
+/// # Arguments
+///
+/// * `manifest` - The parsed `Cargo.toml`.
+/// * `dir` - The directory containing the manifest, which `path` dependencies are relative to.
+/// * `workspace` - The `[workspace]` table of the enclosing workspace and its root directory,
+///   if any.
+///
+/// # Returns
+///
+/// The dependencies sorted by kind, platform and name.
+pub fn parse(manifest: &Value, dir: &Path, workspace: Option<(&Value, &Path)>) -> Vec<Dependency> {
+    let mut sections: Vec<(Option<String>, &Value)> = vec![(None, manifest)];
+    if let Some(targets) = manifest.get("target").and_then(Value::as_table) {
+        sections.extend(targets.iter().map(|(platform, section)| (Some(platform.clone()), section)));
+    }
+    let shared = workspace.and_then(|(table, root)| Some((table.get("dependencies")?, root)));
+
+    let mut dependencies = Vec::new();
+    for (target, section) in sections {
+        for (key, kind) in TABLES {
+            let Some(table) = section.get(*key).and_then(Value::as_table) else {
+                continue;
+            };
+            for (name, entry) in table {
+                let mut dependency = if workspace::is_inherited(entry) {
+                    match shared.and_then(|(shared, root)| Some((shared.get(name)?, root))) {
+                        Some((declared, root)) => {
+                            let mut dependency = read_entry(name, *kind, declared, root);
+                            dependency.inherited = true;
+                            dependency.features.extend(string_list(entry.get("features")));
+                            dependency
+                        }
+                        None => Dependency { source: Source::Workspace, inherited: true, ..read_entry(name, *kind, entry, dir) },
+                    }
+                } else {
+                    read_entry(name, *kind, entry, dir)
+                };
+                // Only the member decides whether the dependency is optional
+                dependency.optional = entry.get("optional").and_then(Value::as_bool).unwrap_or(false);
+                dependency.target = target.clone();
+                dependencies.push(dependency);
+            }
+        }
+    }
+    dependencies.sort_by(|a, b| (a.kind, &a.target, &a.name).cmp(&(b.kind, &b.target, &b.name)));
+    dependencies
+}
+
+/// Returns `true` if any dependency of a manifest is written as `{ workspace = true }`.
+pub fn uses_workspace(manifest: &Value) -> bool {
+    let targets = manifest.get("target").and_then(Value::as_table).into_iter().flat_map(|t| t.values());
+    std::iter::once(manifest).chain(targets).any(|section| {
+        TABLES.iter().any(|(key, _)| {
+            section
+                .get(*key)
+                .and_then(Value::as_table)
+                .is_some_and(|table| table.values().any(workspace::is_inherited))
+        })
+    })
+}
+
+/// Reads a single dependency entry, either a version string or a table.
+///
+/// # Arguments
+///
+/// * `name` - The key of the entry.
+/// * `kind` - The table the entry was found in.
+/// * `entry` - The entry's value.
+/// * `dir` - The directory `path` is relative to.
+fn read_entry(name: &str, kind: DependencyKind, entry: &Value, dir: &Path) -> Dependency {
+    let string = |key: &str| entry.get(key).and_then(Value::as_str).map(String::from);
+    let source = if let Some(url) = string("git") {
+        let reference = ["branch", "tag", "rev"].iter().find_map(|key| Some((key.to_string(), string(key)?)));
+        Source::Git { url, reference }
+    } else if let Some(path) = string("path") {
+        Source::Path(normalize(&dir.join(path)))
+    } else {
+        Source::Registry(string("registry"))
+    };
+    let default_features = ["default-features", "default_features"]
+        .iter()
+        .find_map(|key| entry.get(*key).and_then(Value::as_bool))
+        .unwrap_or(true);
+
+    Dependency {
+        name: name.to_string(),
+        package: string("package"),
+        kind,
+        source,
+        req: entry.as_str().map(String::from).or_else(|| string("version")),
+        optional: false,
+        default_features,
+        features: string_list(entry.get("features")),
+        target: None,
+        inherited: false,
+    }
+}
+
+/// Reads an optional TOML array of strings, skipping elements that are not strings.
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Removes `.` and `..` components from a path without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Formats dependencies as aligned lines for the details view and `deps`.
+///
+/// Each line has the kind, the name, the version requirement and the source, followed by
+/// the platform, `optional`, disabled default features and the enabled features when set.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to format.
+/// * `indent` - The text each line starts with.
+pub fn lines(dependencies: &[Dependency], indent: &str) -> Vec<String> {
+    let names: Vec<String> = dependencies
+        .iter()
+        .map(|d| match &d.package {
+            Some(package) => format!("{} ({})", d.name, package),
+            None => d.name.clone(),
+        })
+        .collect();
+    // A registry dependency without a requirement takes any version; others have none at all
+    let reqs: Vec<&str> = dependencies
+        .iter()
+        .map(|d| d.req.as_deref().unwrap_or(if matches!(d.source, Source::Registry(_)) { "*" } else { "-" }))
+        .collect();
+    let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let req_width = reqs.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+
+    dependencies
+        .iter()
+        .zip(names.iter().zip(&reqs))
+        .map(|(dependency, (name, req))| {
+            let mut notes = Vec::new();
+            if let Some(target) = &dependency.target {
+                notes.push(format!("for {}", target));
+            }
+            if dependency.optional {
+                notes.push("optional".to_string());
+            }
+            if !dependency.default_features {
+                notes.push("no default features".to_string());
+            }
+            if !dependency.features.is_empty() {
+                notes.push(format!("features: {}", dependency.features.join(", ")));
+            }
+            let source = if dependency.inherited && dependency.source != Source::Workspace {
+                format!("{} (from workspace)", dependency.source)
+            } else {
+                dependency.source.to_string()
+            };
+            let notes = if notes.is_empty() { String::new() } else { format!("; {}", notes.join("; ")) };
+            format!("{}{:<6}  {:<name_width$}  {:<req_width$}  {}{}", indent, dependency.kind, name, req, source, notes)
+        })
+        .collect()
+}
+
+/// Builds the machine-readable record of a dependency.
+///
+/// Unset optional fields are left out, as in project records.
+pub fn record(dependency: &Dependency) -> Value {
+    let mut record = Table::new();
+    record.insert("name".into(), Value::String(dependency.name.clone()));
+    if let Some(package) = &dependency.package {
+        record.insert("package".into(), Value::String(package.clone()));
+    }
+    record.insert("kind".into(), Value::String(dependency.kind.to_string()));
+    if let Some(target) = &dependency.target {
+        record.insert("target".into(), Value::String(target.clone()));
+    }
+    record.insert("source".into(), Value::String(dependency.source.name().into()));
+    match &dependency.source {
+        Source::Registry(Some(registry)) => {
+            record.insert("registry".into(), Value::String(registry.clone()));
+        }
+        Source::Git { url, reference } => {
+            record.insert("git".into(), Value::String(url.clone()));
+            if let Some((key, value)) = reference {
+                record.insert(key.clone(), Value::String(value.clone()));
+            }
+        }
+        Source::Path(path) => {
+            record.insert("path".into(), Value::String(path.display().to_string()));
+        }
+        Source::Registry(None) | Source::Workspace => {}
+    }
+    if let Some(req) = &dependency.req {
+        record.insert("req".into(), Value::String(req.clone()));
+    }
+    record.insert("optional".into(), Value::Boolean(dependency.optional));
+    record.insert("default-features".into(), Value::Boolean(dependency.default_features));
+    record.insert("features".into(), Value::Array(dependency.features.iter().cloned().map(Value::String).collect()));
+    record.insert("inherited".into(), Value::Boolean(dependency.inherited));
+    Value::Table(record)
+}
+
+/// Reads a dependency record written by `record`, or `None` if it is malformed.
+pub fn from_record(value: &Value) -> Option<Dependency> {
+    let string = |key: &str| value.get(key).and_then(Value::as_str).map(String::from);
+    let source = match string("source")?.as_str() {
+        "registry" => Source::Registry(string("registry")),
+        "git" => Source::Git {
+            url: string("git")?,
+            reference: ["branch", "tag", "rev"].iter().find_map(|key| Some((key.to_string(), string(key)?))),
+        },
+        "path" => Source::Path(PathBuf::from(string("path")?)),
+        "workspace" => Source::Workspace,
+        _ => return None,
+    };
+    Some(Dependency {
+        name: string("name")?,
+        package: string("package"),
+        kind: DependencyKind::from_name(&string("kind")?)?,
+        source,
+        req: string("req"),
+        optional: value.get("optional")?.as_bool()?,
+        default_features: value.get("default-features")?.as_bool()?,
+        features: value.get("features")?.as_array()?.iter().map(|f| f.as_str().map(String::from)).collect::<Option<_>>()?,
+        target: string("target"),
+        inherited: value.get("inherited")?.as_bool()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[dependencies]
+serde = { version = "1.0", features = ["derive"], optional = true }
+json = { package = "serde_json", version = "1", default-features = false }
+local = { path = "../local" }
+fork = { git = "https://example.com/fork.git", tag = "v2" }
+tokio = { workspace = true, features = ["rt"] }
+missing = { workspace = true }
+
+[dev_dependencies]
+pretty = "0.3"
+
+[build-dependencies]
+cc = "1.0"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#;
+
+    const WORKSPACE: &str = r#"
+[dependencies]
+tokio = { version = "1.38", features = ["macros"], default-features = false }
+"#;
+
+    #[test]
+    fn dependencies_are_read_from_every_table() {
+        let manifest: Value = MANIFEST.parse().expect("valid manifest");
+        let workspace: Value = WORKSPACE.parse().expect("valid workspace table");
+        let dependencies = parse(&manifest, Path::new("/ws/app"), Some((&workspace, Path::new("/ws"))));
+        let find = |name: &str| dependencies.iter().find(|d| d.name == name).unwrap_or_else(|| panic!("{} was not read", name));
+
+        // Sorted by kind, then platform, then name
+        let order: Vec<(&str, DependencyKind)> = dependencies.iter().map(|d| (d.name.as_str(), d.kind)).collect();
+        let normal = ["fork", "json", "local", "missing", "serde", "tokio", "libc"].map(|name| (name, DependencyKind::Normal));
+        assert_eq!(order, [&normal[..], &[("pretty", DependencyKind::Dev), ("cc", DependencyKind::Build)]].concat());
+
+        let serde = find("serde");
+        assert_eq!((serde.req.as_deref(), serde.optional, serde.features.as_slice()), (Some("1.0"), true, &["derive".to_string()][..]));
+        let json = find("json");
+        assert_eq!((json.package.as_deref(), json.default_features), (Some("serde_json"), false));
+        assert_eq!(find("local").source, Source::Path(PathBuf::from("/ws/local")));
+        assert_eq!(find("fork").source, Source::Git { url: "https://example.com/fork.git".into(), reference: Some(("tag".into(), "v2".into())) });
+        assert_eq!(find("libc").target.as_deref(), Some("cfg(unix)"));
+
+        let tokio = find("tokio");
+        assert!(tokio.inherited && !tokio.default_features);
+        assert_eq!(tokio.req.as_deref(), Some("1.38"));
+        assert_eq!(tokio.features, ["macros", "rt"], "the member's features add to the workspace's");
+        let missing = find("missing");
+        assert_eq!((&missing.source, missing.inherited), (&Source::Workspace, true));
+    }
+
+    #[test]
+    fn records_read_back() {
+        let manifest: Value = MANIFEST.parse().expect("valid manifest");
+        for dependency in parse(&manifest, Path::new("/ws/app"), None) {
+            assert_eq!(from_record(&record(&dependency)).as_ref(), Some(&dependency));
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use toml::value::Table;
+use toml::Value;
+
+use crate::{artifacts, output, util, ProjectInfo};
+
+/// The disk usage of one target directory, which may be shared by several projects.
+#[derive(Debug, Clone)]
+pub struct TargetUsage {
+    /// The target directory.
+    pub dir: PathBuf,
+    /// The names of the projects that build into this directory.
+    pub projects: Vec<String>,
+    /// The space taken by everything in the directory, in bytes.
+    pub size: u64,
+    /// When anything in the directory was last written, i.e. roughly the last build.
+    pub last_build: Option<SystemTime>,
+    /// The profile directories and other entries inside, largest first.
+    pub parts: Vec<Part>,
+}
+
+/// A removable part of a target directory, such as `debug`, `release` or `doc`.
+#[derive(Debug, Clone)]
+pub struct Part {
+    /// The path relative to the target directory, e.g. `release` or `x86_64-unknown-linux-gnu/debug`.
+    pub name: String,
+    /// The full path.
+    pub path: PathBuf,
+    /// The space taken by the part, in bytes.
+    pub size: u64,
+    /// When anything in the part was last written.
+    pub last_build: Option<SystemTime>,
+}
+
+/// Measures every target directory that the given projects build into.
+///
+/// Each project's target directories come from `artifacts::target_dirs`, so a shared
+/// `CARGO_TARGET_DIR` or `build.target-dir` is measured once and lists every project
+/// using it. Directories that do not exist, or do not look like a Cargo target directory,
+/// are left out.
+///
+/// # Arguments
+///
+/// * `projects` - The projects whose target directories to measure.
+/// * `labels` - The name each project is shown under, in the same order.
+///
+/// # Returns
+///
+/// The target directories, largest first.
+pub fn usage(projects: &[&ProjectInfo], labels: &[String]) -> Vec<TargetUsage> {
+    let mut owners: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for (info, label) in projects.iter().zip(labels) {
+        for dir in artifacts::target_dirs(&info.path) {
+            if is_target_dir(&dir) {
+                let names = owners.entry(dir).or_default();
+                if !names.contains(label) {
+                    names.push(label.clone());
+                }
+            }
+        }
+    }
+
+    let mut usages: Vec<TargetUsage> = owners
+        .into_iter()
+        .map(|(dir, projects)| {
+            let parts = parts(&dir);
+            let (size, last_build) = measure(&dir, &mut HashSet::new());
+            TargetUsage { dir, projects, size, last_build, parts }
+        })
+        .collect();
+    usages.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.dir.cmp(&b.dir)));
+    usages
+}
+
+/// Finds the measured target directories that projects outside the selection also build into.
+///
+/// Removing such a shared directory, or a profile inside it, would delete the build output
+/// of projects that the filters left out.
+///
+/// # Arguments
+///
+/// * `usages` - The target directories of the selected projects, as returned by `usage`.
+/// * `others` - The scanned projects that were not selected.
+/// * `labels` - The name each of `others` is shown under, in the same order.
+///
+/// # Returns
+///
+/// Each shared directory with the names of the unselected projects building into it.
+pub fn unselected_owners(usages: &[TargetUsage], others: &[&ProjectInfo], labels: &[String]) -> BTreeMap<PathBuf, Vec<String>> {
+    let measured: HashSet<&PathBuf> = usages.iter().map(|usage| &usage.dir).collect();
+    let mut owners: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for (info, label) in others.iter().zip(labels) {
+        for dir in artifacts::target_dirs(&info.path) {
+            if measured.contains(&dir) {
+                let names = owners.entry(dir).or_default();
+                if !names.contains(label) {
+                    names.push(label.clone());
+                }
+            }
+        }
+    }
+    owners
+}
+
+/// Returns `true` if a directory looks like one Cargo builds into.
+///
+/// Cargo marks its target directories with `CACHEDIR.TAG` and `.rustc_info.json`; older
+/// ones are recognised by a `debug` or `release` directory with a `.fingerprint` inside.
+/// Nothing outside such a directory is ever measured as build output or removed.
+pub fn is_target_dir(dir: &Path) -> bool {
+    dir.join("CACHEDIR.TAG").is_file()
+        || dir.join(".rustc_info.json").is_file()
+        || ["debug", "release"].iter().any(|profile| dir.join(profile).join(".fingerprint").is_dir())
+}
+
+/// Returns `true` if a directory holds the output of one profile, e.g. `target/debug`.
+fn is_profile_dir(dir: &Path) -> bool {
+    [".fingerprint", "deps", "build"].iter().any(|d| dir.join(d).is_dir())
+}
+
+/// Splits a target directory into its removable parts.
+///
+/// Every subdirectory is a part, except that cross-compilation directories such as
+/// `x86_64-unknown-linux-gnu` are split further into their own profile directories.
+fn parts(dir: &Path) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return parts;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let nested: Vec<PathBuf> = if is_profile_dir(&path) {
+            Vec::new()
+        } else {
+            fs::read_dir(&path)
+                .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).filter(|p| is_profile_dir(p)).collect())
+                .unwrap_or_default()
+        };
+
+        if nested.is_empty() {
+            let (size, last_build) = measure(&path, &mut HashSet::new());
+            parts.push(Part { name, path, size, last_build });
+        } else {
+            for profile in nested {
+                let profile_name = profile.file_name().unwrap_or_default().to_string_lossy();
+                let (size, last_build) = measure(&profile, &mut HashSet::new());
+                parts.push(Part { name: format!("{}/{}", name, profile_name), path: profile, size, last_build });
+            }
+        }
+    }
+    parts.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    parts
+}
+
+/// Adds up the disk space below a path and finds the newest modification time.
+///
+/// Symbolic links are not followed. Files with several hard links, which Cargo uses for the
+/// executables in `target/<profile>/`, are only counted once.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to measure.
+/// * `seen` - The `(device, inode)` pairs of hard-linked files already counted.
+///
+/// # Returns
+///
+/// The size in bytes and the newest modification time, if any.
+pub fn measure(path: &Path, seen: &mut HashSet<(u64, u64)>) -> (u64, Option<SystemTime>) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return (0, None);
+    };
+    let mut newest = metadata.modified().ok();
+    let mut size = disk_size(&metadata, seen);
+
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.filter_map(Result::ok) {
+                let (entry_size, entry_newest) = measure(&entry.path(), seen);
+                size += entry_size;
+                newest = newest.max(entry_newest);
+            }
+        }
+    }
+    (size, newest)
+}
+
+/// Returns the space a file takes on disk, or 0 for a hard link that was already counted.
+#[cfg(unix)]
+fn disk_size(metadata: &fs::Metadata, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() > 1 && !metadata.is_dir() && !seen.insert((metadata.dev(), metadata.ino())) {
+        return 0;
+    }
+    metadata.blocks() * 512
+}
+
+/// Returns the size of a file.
+#[cfg(not(unix))]
+fn disk_size(metadata: &fs::Metadata, _seen: &mut HashSet<(u64, u64)>) -> u64 {
+    metadata.len()
+}
+
+/// A unit of build output left behind by a toolchain other than the one used last.
+#[derive(Debug, Clone)]
+pub struct StaleUnit {
+    /// The unit name with its hash, e.g. `serde-1a2b3c4d5e6f7a8b`.
+    pub name: String,
+    /// The files and directories that belong to the unit.
+    pub paths: Vec<PathBuf>,
+    /// The space taken by the unit, in bytes.
+    pub size: u64,
+    /// When the unit was last written.
+    pub last_build: Option<SystemTime>,
+}
+
+/// Finds the build units in a profile directory that were compiled by an older toolchain.
+///
+/// Cargo records a hash of the compiler in every fingerprint under `.fingerprint/`. The
+/// compiler of the most recently written fingerprint counts as the current one; units whose
+/// fingerprints name another compiler cannot be reused by it, so their fingerprint, their
+/// `deps/` files and their `build/` directory only take up space.
+///
+/// # Arguments
+///
+/// * `profile_dir` - A profile directory, e.g. `target/debug`.
+///
+/// # Returns
+///
+/// The stale units, largest first.
+pub fn stale_toolchain_units(profile_dir: &Path) -> Vec<StaleUnit> {
+    let Ok(entries) = fs::read_dir(profile_dir.join(".fingerprint")) else {
+        return Vec::new();
+    };
+    let units: Vec<(String, u64, SystemTime)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let (rustc, modified) = fingerprint_rustc(&entry.path())?;
+            Some((entry.file_name().to_string_lossy().into_owned(), rustc, modified))
+        })
+        .collect();
+    let Some(current) = units.iter().max_by_key(|(_, _, modified)| *modified).map(|(_, rustc, _)| *rustc) else {
+        return Vec::new();
+    };
+
+    let deps: Vec<PathBuf> = fs::read_dir(profile_dir.join("deps"))
+        .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    let mut stale: Vec<StaleUnit> = units
+        .into_iter()
+        .filter(|(_, rustc, _)| *rustc != current)
+        .map(|(name, _, _)| {
+            let hash = name.rsplit('-').next().unwrap_or_default().to_string();
+            let mut paths = vec![profile_dir.join(".fingerprint").join(&name)];
+            let build = profile_dir.join("build").join(&name);
+            if build.exists() {
+                paths.push(build);
+            }
+            paths.extend(deps.iter().filter(|p| belongs_to(p, &hash)).cloned());
+
+            let mut seen = HashSet::new();
+            let (mut size, mut last_build) = (0, None);
+            for path in &paths {
+                let (part_size, part_newest) = measure(path, &mut seen);
+                size += part_size;
+                last_build = last_build.max(part_newest);
+            }
+            StaleUnit { name, paths, size, last_build }
+        })
+        .collect();
+    stale.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    stale
+}
+
+/// Returns `true` if a file in `deps/` was produced for the unit with the given hash.
+///
+/// Cargo names these files `<crate>-<hash>` with an optional extension, e.g. `libserde-<hash>.rlib`.
+fn belongs_to(path: &Path, hash: &str) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.split('.').next().unwrap_or_default();
+    !hash.is_empty() && stem.ends_with(&format!("-{}", hash))
+}
+
+/// Reads the compiler hash from a fingerprint directory, with the directory's modification time.
+///
+/// The fingerprint JSON files hold it as `"rustc":<number>`.
+fn fingerprint_rustc(dir: &Path) -> Option<(u64, SystemTime)> {
+    let modified = fs::metadata(dir).and_then(|m| m.modified()).ok()?;
+    for entry in fs::read_dir(dir).ok()?.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some(start) = text.find("\"rustc\":") {
+            let digits: String = text[start + 8..].chars().skip_while(|c| c.is_whitespace()).take_while(char::is_ascii_digit).collect();
+            if let Ok(rustc) = digits.parse() {
+                return Some((rustc, modified));
+            }
+        }
+    }
+    None
+}
+
+/// What `clean` removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanMode {
+    /// Whole target directories.
+    Target,
+    /// Single profile directories such as `target/debug`, and other parts such as `target/doc`.
+    Profiles,
+    /// Build units left behind by older toolchains; see `stale_toolchain_units`.
+    Toolchains,
+}
+
+/// The conditions something must meet to be removed by `clean`; all given ones must hold.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Criteria {
+    /// Only remove what was last built longer ago than this.
+    pub older_than: Option<Duration>,
+    /// Only remove what takes up at least this many bytes.
+    pub larger_than: Option<u64>,
+}
+
+impl Criteria {
+    /// Returns `true` if something of the given size and last build time meets the criteria.
+    fn matches(&self, size: u64, last_build: Option<SystemTime>) -> bool {
+        let old_enough = self.older_than.is_none_or(|age| {
+            let cutoff = SystemTime::now().checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+            last_build.is_none_or(|built| built < cutoff)
+        });
+        let large_enough = self.larger_than.is_none_or(|bytes| size >= bytes);
+        old_enough && large_enough
+    }
+}
+
+/// Something `clean` would remove.
+#[derive(Debug, Clone)]
+pub struct Removal {
+    /// What is removed, e.g. the target directory or `<target dir> debug/serde-1a2b3c`.
+    pub label: String,
+    /// The projects that build into the target directory it is part of.
+    pub projects: Vec<String>,
+    /// The files and directories to delete.
+    pub paths: Vec<PathBuf>,
+    /// The space that is freed, in bytes.
+    pub size: u64,
+    /// When it was last built.
+    pub last_build: Option<SystemTime>,
+}
+
+/// Works out what `clean` removes from the measured target directories.
+///
+/// # Arguments
+///
+/// * `usages` - The target directories, as returned by `usage`.
+/// * `mode` - Whether whole target directories, single profiles or stale toolchain units are removed.
+/// * `criteria` - The age and size conditions; in `Toolchains` mode they apply to each unit.
+///
+/// # Returns
+///
+/// The removals, in the order of `usages` and, within one directory, largest first.
+pub fn removals(usages: &[TargetUsage], mode: CleanMode, criteria: &Criteria) -> Vec<Removal> {
+    let mut removals = Vec::new();
+    for usage in usages {
+        let removal = |label: String, paths: Vec<PathBuf>, size: u64, last_build: Option<SystemTime>| Removal {
+            label,
+            projects: usage.projects.clone(),
+            paths,
+            size,
+            last_build,
+        };
+        match mode {
+            CleanMode::Target => {
+                if criteria.matches(usage.size, usage.last_build) {
+                    removals.push(removal(usage.dir.display().to_string(), vec![usage.dir.clone()], usage.size, usage.last_build));
+                }
+            }
+            CleanMode::Profiles => {
+                for part in usage.parts.iter().filter(|p| criteria.matches(p.size, p.last_build)) {
+                    removals.push(removal(part.path.display().to_string(), vec![part.path.clone()], part.size, part.last_build));
+                }
+            }
+            CleanMode::Toolchains => {
+                for part in usage.parts.iter().filter(|p| is_profile_dir(&p.path)) {
+                    for unit in stale_toolchain_units(&part.path) {
+                        if criteria.matches(unit.size, unit.last_build) {
+                            let label = format!("{} {}/{}", usage.dir.display(), part.name, unit.name);
+                            removals.push(removal(label, unit.paths, unit.size, unit.last_build));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    removals
+}
+
+/// Deletes the files and directories of a removal.
+///
+/// # Returns
+///
+/// `Ok` if everything was deleted, or the first error.
+pub fn remove(removal: &Removal) -> Result<(), String> {
+    for path in &removal.paths {
+        let result = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Could not remove {}: {}", path.display(), e)),
+        }
+    }
+    Ok(())
+}
+
+/// Prints the disk usage report: one entry per target directory, largest first, and the total.
+///
+/// # Arguments
+///
+/// * `usages` - The target directories, as returned by `usage`.
+pub fn print_usage(usages: &[TargetUsage]) {
+    if usages.is_empty() {
+        println!("No target directories found.");
+        return;
+    }
+    println!("{:>10}  {:<16}  Target directory", "Size", "Last build");
+    for usage in usages {
+        println!("{:>10}  {:<16}  {}", util::format_size(usage.size), last_build_text(usage.last_build), usage.dir.display());
+        println!("{:>10}  {:<16}  used by {}", "", "", usage.projects.join(", "));
+        let parts: Vec<String> = usage.parts.iter().map(|p| format!("{} {}", p.name, util::format_size(p.size))).collect();
+        if !parts.is_empty() {
+            println!("{:>10}  {:<16}  {}", "", "", parts.join(", "));
+        }
+    }
+    let total: u64 = usages.iter().map(|u| u.size).sum();
+    println!();
+    println!(
+        "Total: {} in {} target director{}",
+        util::format_size(total),
+        usages.len(),
+        if usages.len() == 1 { "y" } else { "ies" }
+    );
+}
+
+/// Converts the disk usage report into records for machine-readable output.
+pub fn usage_records(usages: &[TargetUsage]) -> Vec<Value> {
+    usages
+        .iter()
+        .map(|usage| {
+            let mut record = Table::new();
+            record.insert("path".into(), Value::String(usage.dir.display().to_string()));
+            record.insert("size".into(), Value::Integer(usage.size as i64));
+            if let Some(time) = usage.last_build {
+                record.insert("last-build".into(), output::time_value(time));
+            }
+            record.insert("projects".into(), Value::Array(usage.projects.iter().cloned().map(Value::String).collect()));
+            let parts = usage
+                .parts
+                .iter()
+                .map(|part| {
+                    let mut table = Table::new();
+                    table.insert("name".into(), Value::String(part.name.clone()));
+                    table.insert("size".into(), Value::Integer(part.size as i64));
+                    if let Some(time) = part.last_build {
+                        table.insert("last-build".into(), output::time_value(time));
+                    }
+                    Value::Table(table)
+                })
+                .collect();
+            record.insert("parts".into(), Value::Array(parts));
+            Value::Table(record)
+        })
+        .collect()
+}
+
+/// Prints what `clean` removes or would remove, one line each, and the total size.
+pub fn print_removals(removals: &[Removal]) {
+    for removal in removals {
+        println!(
+            "{:>10}  {:<16}  {}  ({})",
+            util::format_size(removal.size),
+            last_build_text(removal.last_build),
+            removal.label,
+            removal.projects.join(", ")
+        );
+    }
+}
+
+/// Describes when something was last built, e.g. `3 days ago`, or `never` if it is empty.
+fn last_build_text(last_build: Option<SystemTime>) -> String {
+    last_build.map(util::format_age).unwrap_or_else(|| "never".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    fn project(path: PathBuf, name: &str) -> ProjectInfo {
+        ProjectInfo { name: name.into(), path, ..ProjectInfo::default() }
+    }
+
+    #[test]
+    fn a_shared_target_directory_is_measured_once() {
+        let dir = scratch_dir("disk-usage");
+        let ws = dir.join("ws");
+        write_file(&ws.join("Cargo.toml"), "[workspace]\nmembers = [\"a\", \"b\"]\n");
+        let target = ws.join("target");
+        write_file(&target.join("CACHEDIR.TAG"), "Signature: 8a477f597d28d172789f06886806bc55\n");
+        write_file(&target.join("release/.fingerprint/app/hash"), &"x".repeat(256 * 1024));
+        write_file(&target.join("debug/deps/app"), "x");
+        write_file(&target.join("x86_64-unknown-linux-gnu/debug/deps/app"), "x");
+        fs::hard_link(target.join("release/.fingerprint/app/hash"), target.join("release/app")).expect("create hard link");
+        // A `target/` that Cargo did not create is never measured
+        write_file(&dir.join("solo/target/notes.txt"), "x");
+
+        let (a, b, solo) = (project(ws.join("a"), "a"), project(ws.join("b"), "b"), project(dir.join("solo"), "solo"));
+        let labels = ["a".to_string(), "b".to_string(), "solo".to_string()];
+        let mut usages = usage(&[&a, &b, &solo], &labels);
+        usages.retain(|usage| usage.dir.starts_with(&dir));
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].dir, target);
+        assert_eq!(usages[0].projects, ["a", "b"]);
+        assert!(usages[0].size < 2 * 256 * 1024, "hard links are counted once");
+        let parts: Vec<&str> = usages[0].parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(parts[0], "release", "the largest part comes first");
+        let mut rest = parts[1..].to_vec();
+        rest.sort();
+        assert_eq!(rest, ["debug", "x86_64-unknown-linux-gnu/debug"]);
+
+        let large = Criteria { larger_than: Some(64 * 1024), ..Criteria::default() };
+        let removed: Vec<PathBuf> = removals(&usages, CleanMode::Profiles, &large).into_iter().flat_map(|r| r.paths).collect();
+        assert_eq!(removed, [target.join("release")]);
+        let removed = removals(&usages, CleanMode::Target, &Criteria::default());
+        assert_eq!(removed.iter().map(|r| r.paths.clone()).collect::<Vec<_>>(), [vec![target.clone()]]);
+
+        let selected = usage(&[&a], &labels[..1]);
+        let owners = unselected_owners(&selected, &[&b, &solo], &labels[1..]);
+        assert_eq!(owners.get(&target), Some(&vec!["b".to_string()]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
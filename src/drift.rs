@@ -0,0 +1,532 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+use toml::Value;
+
+use crate::deps::Source;
+use semver::{Comparator, Op, Version, VersionReq};
+
+use crate::{workspace, ProjectInfo};
+
+/// How many project names are shown per version before the rest are counted.
+const SHOWN_PROJECTS: usize = 5;
+
+/// A crate that the projects use at more than one version.
+pub struct Drift {
+    /// The crate name.
+    pub name: String,
+    /// The versions in use grouped by semver-compatible range, e.g. `1.x` or `0.8.x`, newest
+    /// range first. A requirement allowing several ranges is grouped under all of them,
+    /// e.g. `1.x-2.x` for `>=1.2, <3`.
+    pub groups: Vec<(String, Vec<Usage>)>,
+    /// How many projects use the crate at any version.
+    pub projects: usize,
+    /// Whether some versions share no semver-compatible range.
+    incompatible: bool,
+}
+
+impl Drift {
+    /// Returns `true` if the versions span more than one semver-compatible range, so that
+    /// aligning them takes more than `cargo update`.
+    pub fn is_incompatible(&self) -> bool {
+        self.incompatible
+    }
+}
+
+/// One version of a crate and the projects using it.
+pub struct Usage {
+    /// The locked version, or the version requirement for projects without a `Cargo.lock`.
+    pub version: String,
+    /// Whether the version comes from a `Cargo.lock` rather than a requirement in `Cargo.toml`.
+    pub locked: bool,
+    /// The names of the projects using this version.
+    pub projects: Vec<String>,
+}
+
+/// The versions of the registry crates a single project uses.
+struct Versions {
+    /// The project's name.
+    project: String,
+    /// The crate names and versions.
+    crates: Vec<(String, String)>,
+    /// Whether the versions come from a `Cargo.lock`.
+    locked: bool,
+}
+
+/// Finds the crates that the projects use at more than one version.
+///
+/// A crate drifts when the projects using it differ: when their lockfiles have different
+/// versions of it, or when their versions and requirements span more than one
+/// semver-compatible range. A requirement compatible with a locked version is not counted
+/// as a difference, and neither are several versions in one project's lockfile.
+///
+/// Each project's versions come from its `Cargo.lock`, or the one at the root of the
+/// enclosing workspace, and only registry packages are counted. Projects without a lockfile
+/// contribute the version requirements of their registry dependencies and those of their
+/// workspace members instead. Projects sharing a lockfile are counted once, under the name
+/// of the first one.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to compare.
+/// * `labels` - The name each project is shown under, in the same order.
+///
+/// # Returns
+///
+/// The drifting crates: those spanning several semver-compatible ranges first, then the
+/// others, each ranked by how many projects use the crate.
+pub fn find(projects: &[&ProjectInfo], labels: &[String]) -> Vec<Drift> {
+    let mut seen_locks: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut all = Vec::new();
+    for (info, label) in projects.iter().zip(labels) {
+        let lock = lockfile(&info.path);
+        match lock.as_ref().and_then(|lock| Some((lock, locked_versions(lock)?))) {
+            Some((lock, crates)) => {
+                if seen_locks.insert(lock.clone()) {
+                    all.push(Versions { project: label.clone(), crates, locked: true });
+                }
+            }
+            None => all.push(Versions { project: label.clone(), crates: required_versions(info), locked: false }),
+        }
+    }
+
+    // crate -> (version, locked) -> projects
+    let mut usages: BTreeMap<String, BTreeMap<(String, bool), BTreeSet<String>>> = BTreeMap::new();
+    for versions in &all {
+        for (name, version) in &versions.crates {
+            usages
+                .entry(name.clone())
+                .or_default()
+                .entry((version.clone(), versions.locked))
+                .or_default()
+                .insert(versions.project.clone());
+        }
+    }
+
+    let mut drifts: Vec<Drift> = usages
+        .into_iter()
+        .filter_map(|(name, versions)| {
+            // Versions and requirements that do not parse are left out
+            let usages: Vec<(Usage, Span)> = versions
+                .into_iter()
+                .filter_map(|((version, locked), users)| {
+                    let span = span(&version, locked)?;
+                    Some((Usage { version, locked, projects: users.into_iter().collect() }, span))
+                })
+                .collect();
+            let (drifting, incompatible) = compare_projects(&usages);
+            if !drifting {
+                return None;
+            }
+
+            let projects = usages.iter().flat_map(|(usage, _)| &usage.projects).collect::<BTreeSet<_>>().len();
+            // Keyed so that the newest range, and the unbounded ones, come last
+            let mut groups: BTreeMap<(bool, Option<Series>, Series), Vec<Usage>> = BTreeMap::new();
+            for (usage, (first, last)) in usages {
+                groups.entry((last.is_none(), last, first)).or_default().push(usage);
+            }
+            let groups = groups
+                .into_iter()
+                .rev()
+                .map(|((_, last, first), mut usages)| {
+                    usages.sort_by_key(|usage| std::cmp::Reverse(sort_key(usage)));
+                    (span_text(&(first, last)), usages)
+                })
+                .collect();
+            Some(Drift { name, groups, projects, incompatible })
+        })
+        .collect();
+    drifts.sort_by(|a, b| {
+        (b.is_incompatible(), b.projects, &a.name).cmp(&(a.is_incompatible(), a.projects, &b.name))
+    });
+    drifts
+}
+
+/// Finds the `Cargo.lock` that applies to a project: its own, or the workspace root's.
+pub fn lockfile(dir: &Path) -> Option<PathBuf> {
+    let own = dir.join("Cargo.lock");
+    if own.is_file() {
+        return Some(own);
+    }
+    let root = workspace::enclosing_root(dir)?.join("Cargo.lock");
+    root.is_file().then_some(root)
+}
+
+/// Reads the names and versions of the registry packages in a `Cargo.lock`.
+///
+/// Packages without a `source` are the workspace's own, and those with a `git+` source are
+/// not versioned by a registry, so both are left out.
+pub fn locked_versions(lock: &Path) -> Option<Vec<(String, String)>> {
+    let document: Value = fs::read_to_string(lock).ok()?.parse().ok()?;
+    let packages = document.get("package")?.as_array()?;
+    Some(
+        packages
+            .iter()
+            .filter(|package| {
+                package
+                    .get("source")
+                    .and_then(Value::as_str)
+                    .is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+"))
+            })
+            .filter_map(|package| {
+                let name = package.get("name")?.as_str()?;
+                let version = package.get("version")?.as_str()?;
+                Some((name.to_string(), version.to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// Reads the version requirements of the registry dependencies of a project and its members.
+fn required_versions(info: &ProjectInfo) -> Vec<(String, String)> {
+    let mut crates: Vec<(String, String)> = std::iter::once(info)
+        .chain(&info.members)
+        .flat_map(|package| &package.dependencies)
+        .filter(|dependency| matches!(dependency.source, Source::Registry(_)))
+        .filter_map(|dependency| {
+            let name = dependency.package.as_deref().unwrap_or(&dependency.name);
+            Some((name.to_string(), dependency.req.clone()?))
+        })
+        .collect();
+    crates.sort();
+    crates.dedup();
+    crates
+}
+
+/// A series of semver-compatible versions: those sharing their first non-zero part, the way
+/// Cargo treats them. `1.2.3` and `1.9.0` are both in `1.x`, while `0.8.1` is in `0.8.x`
+/// and `0.0.3` is a series of its own. Series order from oldest to newest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Series {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl Series {
+    /// Returns the series of a `major.minor.patch` triple.
+    fn of((major, minor, patch): (u64, u64, u64)) -> Series {
+        if major > 0 {
+            Series { major, minor: 0, patch: 0 }
+        } else if minor > 0 {
+            Series { major, minor, patch: 0 }
+        } else {
+            Series { major, minor, patch }
+        }
+    }
+}
+
+impl fmt::Display for Series {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.major > 0 {
+            write!(f, "{}.x", self.major)
+        } else if self.minor > 0 {
+            write!(f, "0.{}.x", self.minor)
+        } else {
+            write!(f, "0.0.{}", self.patch)
+        }
+    }
+}
+
+/// The semver-compatible ranges a version or requirement allows: the first and the last,
+/// which is `None` when there is no upper bound.
+type Span = (Series, Option<Series>);
+
+/// Compares what the projects using a crate have of it.
+///
+/// Only differences between projects count, so a single lockfile holding both `syn 1.x`
+/// and `syn 2.x` does not drift on its own. Two projects differ when one has a version the
+/// other lacks: for two lockfiles a different locked version, and otherwise a version or
+/// requirement allowing none of the ranges of the other project's.
+///
+/// # Returns
+///
+/// Whether the projects differ, and whether some of them share no semver-compatible range.
+fn compare_projects(usages: &[(Usage, Span)]) -> (bool, bool) {
+    let mut profiles: BTreeMap<&str, BTreeSet<usize>> = BTreeMap::new();
+    for (index, (usage, _)) in usages.iter().enumerate() {
+        for project in &usage.projects {
+            profiles.entry(project).or_default().insert(index);
+        }
+    }
+    // Projects with the same usages cannot differ from each other
+    let profiles: BTreeSet<BTreeSet<usize>> = profiles.into_values().collect();
+
+    let (mut drifting, mut incompatible) = (false, false);
+    for a in &profiles {
+        for b in profiles.iter().filter(|b| *b != a) {
+            let b_locked = b.iter().any(|&index| usages[index].0.locked);
+            for &index in a {
+                let (usage, span) = &usages[index];
+                if !b.iter().any(|&other| overlaps(span, &usages[other].1)) {
+                    drifting = true;
+                    incompatible = true;
+                } else if usage.locked && b_locked && !b.contains(&index) {
+                    drifting = true;
+                }
+            }
+        }
+    }
+    (drifting, incompatible)
+}
+
+/// Works out the ranges a locked version or a version requirement allows, or returns
+/// `None` if it does not parse.
+fn span(version: &str, locked: bool) -> Option<Span> {
+    if locked {
+        let version = Version::parse(version).ok()?;
+        let series = Series::of((version.major, version.minor, version.patch));
+        return Some((series, Some(series)));
+    }
+    let req = VersionReq::parse(version).ok()?;
+    let lower = lower_bound(&req);
+    let first = Series::of(lower);
+    let Some((major, minor, patch)) = req.comparators.iter().filter_map(upper_bound).min() else {
+        return Some((first, None));
+    };
+    // The newest release below the exclusive upper bound
+    let highest = if patch > 0 {
+        Some((major, minor, patch - 1))
+    } else if minor > 0 {
+        Some((major, minor - 1, u64::MAX))
+    } else {
+        major.checked_sub(1).map(|major| (major, u64::MAX, u64::MAX))
+    };
+    let last = highest.filter(|highest| *highest >= lower).map_or(first, Series::of);
+    Some((first, Some(last)))
+}
+
+/// Returns the lowest `major.minor.patch` a requirement allows, ignoring pre-releases, e.g.
+/// `1.2.0` for `>=1.2, <3` and `0.0.0` for `*`.
+fn lower_bound(req: &VersionReq) -> (u64, u64, u64) {
+    let lowest = |c: &Comparator| match (c.op, c.minor, c.patch) {
+        (Op::Less | Op::LessEq, _, _) => (0, 0, 0),
+        (Op::Greater, None, _) => (c.major + 1, 0, 0),
+        (Op::Greater, Some(minor), None) => (c.major, minor + 1, 0),
+        (Op::Greater, Some(minor), Some(patch)) if c.pre.is_empty() => (c.major, minor, patch + 1),
+        (_, minor, patch) => (c.major, minor.unwrap_or(0), patch.unwrap_or(0)),
+    };
+    req.comparators.iter().map(lowest).max().unwrap_or_default()
+}
+
+/// Returns the `major.minor.patch` that a comparator allows everything below, or `None` if
+/// it has no upper bound.
+fn upper_bound(c: &Comparator) -> Option<(u64, u64, u64)> {
+    let next = match (c.minor, c.patch) {
+        (None, _) => (c.major + 1, 0, 0),
+        (Some(minor), None) => (c.major, minor + 1, 0),
+        (Some(minor), Some(patch)) => (c.major, minor, patch + 1),
+    };
+    match c.op {
+        Op::Less => Some((c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0))),
+        Op::Exact | Op::Wildcard | Op::LessEq => Some(next),
+        Op::Tilde => Some(c.minor.map_or((c.major + 1, 0, 0), |minor| (c.major, minor + 1, 0))),
+        Op::Caret => Some(match (c.major, c.minor, c.patch) {
+            (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+            (0, Some(minor), _) => (0, minor + 1, 0),
+            _ => (c.major + 1, 0, 0),
+        }),
+        _ => None,
+    }
+}
+
+/// Returns `true` if two spans share at least one range.
+fn overlaps(a: &Span, b: &Span) -> bool {
+    a.1.is_none_or(|last| b.0 <= last) && b.1.is_none_or(|last| a.0 <= last)
+}
+
+/// Returns a span as shown, e.g. `0.8.x`, `1.x-2.x`, `>=1.x` or `*`.
+fn span_text(span: &Span) -> String {
+    match span {
+        (first, Some(last)) if first == last => first.to_string(),
+        (first, Some(last)) => format!("{}-{}", first, last),
+        (first, None) if *first == Series::default() => "*".to_string(),
+        (first, None) => format!(">={}", first),
+    }
+}
+
+/// Orders the usages of one range when sorted in reverse: locked versions newest first, then
+/// requirements by the lowest version they allow, highest first.
+fn sort_key(usage: &Usage) -> (bool, Option<Version>) {
+    let version = if usage.locked {
+        Version::parse(&usage.version).ok()
+    } else {
+        let lowest = VersionReq::parse(&usage.version).map(|req| lower_bound(&req));
+        lowest.ok().map(|(major, minor, patch)| Version::new(major, minor, patch))
+    };
+    (usage.locked, version)
+}
+
+/// Prints the drift report.
+///
+/// Crates whose versions span several semver-compatible ranges come first, then those whose
+/// versions differ within one range. Each version is followed by the projects using it;
+/// requirements read from `Cargo.toml` are marked `(required)`.
+pub fn print_drift(drifts: &[Drift], projects: usize) {
+    if drifts.is_empty() {
+        println!("No crate versions differ across {} project{}.", projects, if projects == 1 { "" } else { "s" });
+        return;
+    }
+    let sections = [
+        (true, "Different semver-incompatible versions:"),
+        (false, "Different versions within one compatible range:"),
+    ];
+    let width = drifts
+        .iter()
+        .flat_map(|drift| drift.groups.iter().flat_map(|(_, usages)| usages))
+        .map(|usage| version_text(usage).chars().count())
+        .max()
+        .unwrap_or(0);
+    let ranges = drifts.iter().flat_map(|drift| &drift.groups).map(|(range, _)| range.chars().count()).max().unwrap_or(0).max(8);
+    let mut first = true;
+    for (incompatible, heading) in sections {
+        let section: Vec<&Drift> = drifts.iter().filter(|drift| drift.is_incompatible() == incompatible).collect();
+        if section.is_empty() {
+            continue;
+        }
+        if !first {
+            println!();
+        }
+        first = false;
+        println!("{}", heading);
+        for drift in section {
+            println!("  {} ({} projects)", drift.name, drift.projects);
+            for (range, usages) in &drift.groups {
+                for (index, usage) in usages.iter().enumerate() {
+                    let range = if index == 0 { range.as_str() } else { "" };
+                    println!("    {:<ranges$} {:<width$}  {}", range, version_text(usage), projects_text(&usage.projects));
+                }
+            }
+        }
+    }
+    let incompatible = drifts.iter().filter(|drift| drift.is_incompatible()).count();
+    println!();
+    println!(
+        "{} crate{} at more than one version across {} project{}, {} of them semver-incompatible.",
+        drifts.len(),
+        if drifts.len() == 1 { "" } else { "s" },
+        projects,
+        if projects == 1 { "" } else { "s" },
+        incompatible
+    );
+}
+
+/// Returns the version of a usage as shown, marking requirements read from `Cargo.toml`.
+fn version_text(usage: &Usage) -> String {
+    if usage.locked {
+        usage.version.clone()
+    } else {
+        format!("{} (required)", usage.version)
+    }
+}
+
+/// Returns the number of projects followed by the first few names.
+fn projects_text(projects: &[String]) -> String {
+    let count = format!("{} project{}", projects.len(), if projects.len() == 1 { "" } else { "s" });
+    let shown = projects.iter().take(SHOWN_PROJECTS).cloned().collect::<Vec<_>>().join(", ");
+    match projects.len().checked_sub(SHOWN_PROJECTS) {
+        Some(more) if more > 0 => format!("{}: {} and {} more", count, shown, more),
+        _ => format!("{}: {}", count, shown),
+    }
+}
+
+/// Converts the drift report into records for machine-readable output, one per version of
+/// each crate, in the order of the report.
+pub fn records(drifts: &[Drift]) -> Vec<Value> {
+    let mut records = Vec::new();
+    for (rank, drift) in drifts.iter().enumerate() {
+        for (range, usages) in &drift.groups {
+            for usage in usages {
+                let mut record = Table::new();
+                record.insert("rank".into(), Value::Integer(rank as i64 + 1));
+                record.insert("crate".into(), Value::String(drift.name.clone()));
+                record.insert("version".into(), Value::String(usage.version.clone()));
+                record.insert("locked".into(), Value::Boolean(usage.locked));
+                record.insert("range".into(), Value::String(range.clone()));
+                record.insert("incompatible".into(), Value::Boolean(drift.is_incompatible()));
+                record.insert("crate-projects".into(), Value::Integer(drift.projects as i64));
+                record.insert("count".into(), Value::Integer(usage.projects.len() as i64));
+                record.insert("projects".into(), Value::Array(usage.projects.iter().cloned().map(Value::String).collect()));
+                records.push(Value::Table(record));
+            }
+        }
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the usages of one crate from `(version, locked, projects)`.
+    fn usages(versions: &[(&str, bool, &[&str])]) -> Vec<(Usage, Span)> {
+        versions
+            .iter()
+            .map(|(version, locked, projects)| {
+                let usage = Usage { version: version.to_string(), locked: *locked, projects: projects.iter().map(|p| p.to_string()).collect() };
+                (usage, span(version, *locked).expect("valid version"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_lockfile_with_two_versions_does_not_drift() {
+        assert_eq!(compare_projects(&usages(&[("1.0.109", true, &["a"]), ("2.0.48", true, &["a"])])), (false, false));
+        let same = usages(&[("1.0.109", true, &["a", "b"]), ("2.0.48", true, &["a", "b"])]);
+        assert_eq!(compare_projects(&same), (false, false));
+    }
+
+    #[test]
+    fn lockfiles_with_different_versions_drift() {
+        assert_eq!(compare_projects(&usages(&[("2.0.48", true, &["a"]), ("2.0.90", true, &["b"])])), (true, false));
+        assert_eq!(compare_projects(&usages(&[("1.0.109", true, &["a"]), ("2.0.48", true, &["b"])])), (true, true));
+        let partly = usages(&[("1.0.109", true, &["a"]), ("2.0.48", true, &["a", "b"])]);
+        assert_eq!(compare_projects(&partly), (true, true));
+    }
+
+    #[test]
+    fn requirements_drift_only_outside_the_locked_ranges() {
+        assert_eq!(compare_projects(&usages(&[("2.0.48", true, &["a"]), ("2", false, &["b"])])), (false, false));
+        assert_eq!(compare_projects(&usages(&[("2.0.48", true, &["a"]), (">=1.2, <3", false, &["b"])])), (false, false));
+        assert_eq!(compare_projects(&usages(&[("2.0.48", true, &["a"]), ("1.0", false, &["b"])])), (true, true));
+        assert_eq!(compare_projects(&usages(&[("1.0", false, &["a"]), ("1.2", false, &["b"])])), (false, false));
+    }
+
+    #[test]
+    fn spans_are_shown_by_range() {
+        let text = |version: &str, locked: bool| span(version, locked).map(|span| span_text(&span));
+        assert_eq!(text("0.8.5", true), Some("0.8.x".to_string()));
+        assert_eq!(text(">=1.2, <3", false), Some("1.x-2.x".to_string()));
+        assert_eq!(text(">=1.2", false), Some(">=1.x".to_string()));
+        assert_eq!(text("*", false), Some("*".to_string()));
+        assert_eq!(text("not a version", false), None);
+    }
+
+    #[test]
+    fn series() {
+        let series = |version: &str| span(version, true).map(|(first, _)| first.to_string());
+        assert_eq!(series("1.9.0"), Some("1.x".to_string()));
+        assert_eq!(series("0.8.1"), Some("0.8.x".to_string()));
+        assert_eq!(series("0.0.3"), Some("0.0.3".to_string()));
+
+        let spans = |req: &str| {
+            let (first, last) = span(req, false).expect("valid requirement");
+            (first.to_string(), last.map(|last| last.to_string()))
+        };
+        let expected = |first: &str, last: Option<&str>| (first.to_string(), last.map(String::from));
+        assert_eq!(spans("1.2"), expected("1.x", Some("1.x")));
+        assert_eq!(spans("0.8"), expected("0.8.x", Some("0.8.x")));
+        assert_eq!(spans("^0.0.3"), expected("0.0.3", Some("0.0.3")));
+        assert_eq!(spans("~1.2.3"), expected("1.x", Some("1.x")));
+        assert_eq!(spans("1.*"), expected("1.x", Some("1.x")));
+        assert_eq!(spans(">=1.2, <3"), expected("1.x", Some("2.x")));
+        assert_eq!(spans(">=0.7, <0.9"), expected("0.7.x", Some("0.8.x")));
+        assert_eq!(spans(">=1.2, <2"), expected("1.x", Some("1.x")));
+        assert_eq!(spans("<=2"), expected("0.0.0", Some("2.x")));
+        assert_eq!(spans(">=1.2"), expected("1.x", None));
+        assert_eq!(spans("*"), expected("0.0.0", None));
+        assert_eq!(VersionReq::parse(">1.2, <3").ok().map(|req| lower_bound(&req)), Some((1, 3, 0)));
+    }
+}
@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime};
+
+use crate::targets::TargetKind;
+use crate::{git, output, search, sort, util, ProjectInfo, ProjectKind};
+
+/// The fields a filter expression can test besides the list columns: `loc` and `size`.
+const EXTRA_FIELDS: &[&str] = &["loc", "size"];
+
+/// The columns whose value is a list, where `=` and `!=` test for a single item.
+const LIST_FIELDS: &[&str] = &["authors", "keywords", "categories", "publish", "bins"];
+
+/// How a filter expression compares a field with its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// A condition a project must meet to be listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `<field><op><value>`, e.g. `edition<2021` or `license=MIT`.
+    Compare { field: String, op: Op, value: String },
+    /// A fuzzy query matched like `find` does.
+    Query(String),
+    /// The project has at least one binary target (`--has-bin`).
+    HasBin,
+    /// The project has changed or untracked files in Git (`--dirty`).
+    Dirty,
+    /// No file of the project changed within the given time (`--stale`).
+    Untouched(Duration),
+}
+
+impl Filter {
+    /// Parses the argument of `--filter`.
+    ///
+    /// An argument with one of the operators `=`, `!=`, `<`, `<=`, `>`, `>=` or `~` is an
+    /// expression comparing a field, which is any of the list columns, `loc` or `size`, with a
+    /// value. Anything else is a fuzzy query.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The filter as typed, e.g. `edition<2021`.
+    ///
+    /// # Returns
+    ///
+    /// The filter, or a message naming the unknown field or invalid value.
+    pub fn parse(text: &str) -> Result<Filter, String> {
+        let Some(start) = text.find(['<', '>', '=', '!', '~']) else {
+            return Ok(Filter::Query(text.to_string()));
+        };
+        let field = text[..start].trim().to_lowercase();
+        let rest = &text[start..];
+        let (op, length) = [("<=", Op::Le), (">=", Op::Ge), ("!=", Op::Ne), ("==", Op::Eq), ("=", Op::Eq), ("<", Op::Lt), (">", Op::Gt), ("~", Op::Contains)]
+            .into_iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+            .map(|(symbol, op)| (op, symbol.len()))
+            .ok_or_else(|| format!("Invalid filter `{}`; expected e.g. edition<2021 or license=MIT", text))?;
+        let value = rest[length..].trim().to_string();
+
+        let known = output::COLUMNS.iter().chain(git::COLUMNS).chain(EXTRA_FIELDS);
+        if !known.clone().any(|f| *f == field) {
+            let names: Vec<&str> = known.copied().collect();
+            return Err(format!("Unknown filter field `{}`; expected one of: {}", field, names.join(", ")));
+        }
+        if field == "size" {
+            util::parse_size(&value)?;
+        }
+        Ok(Filter::Compare { field, op, value })
+    }
+
+    /// Returns `true` if a project meets the condition.
+    pub fn matches(&self, info: &ProjectInfo) -> bool {
+        match self {
+            Filter::Compare { field, op, value } => compare(info, field, *op, value),
+            Filter::Query(query) => search::best_match(info, query).is_some(),
+            Filter::HasBin => info.targets.iter().any(|t| t.kind == TargetKind::Bin),
+            Filter::Dirty => git::status(&info.path).is_some_and(|status| status.is_dirty()),
+            Filter::Untouched(age) => {
+                let cutoff = SystemTime::now().checked_sub(*age).unwrap_or(SystemTime::UNIX_EPOCH);
+                sort::last_modified(info).is_none_or(|modified| modified < cutoff)
+            }
+        }
+    }
+}
+
+/// Evaluates a filter expression on a project.
+///
+/// `=` and `!=` ignore case and, on list fields, test whether any item equals the value.
+/// `~` tests whether the field contains the value, ignoring case. The ordering operators
+/// compare numbers and versions component by component, e.g. `1.9 < 1.10`, sizes such as
+/// `2G` for `size`, and text otherwise; a field that is not set never matches them.
+fn compare(info: &ProjectInfo, field: &str, op: Op, value: &str) -> bool {
+    let actual = match field {
+        "loc" => sort::lines_of_code(info).to_string(),
+        "size" => sort::size(info).to_string(),
+        _ => output::column_value(info, None, field),
+    };
+    let equals = |text: &str| text.trim().eq_ignore_ascii_case(value);
+    let is_equal = if LIST_FIELDS.contains(&field) { actual.split(", ").any(equals) } else { equals(&actual) };
+
+    let ordering = || -> Option<Ordering> {
+        if actual.is_empty() {
+            return None;
+        }
+        if field == "size" {
+            return Some(actual.parse::<u64>().ok()?.cmp(&util::parse_size(value).ok()?));
+        }
+        let (left, right) = (util::version_key(&actual), util::version_key(value));
+        if !left.is_empty() && !right.is_empty() {
+            Some(left.cmp(&right))
+        } else {
+            Some(actual.to_lowercase().cmp(&value.to_lowercase()))
+        }
+    };
+
+    match op {
+        Op::Eq => is_equal,
+        Op::Ne => !is_equal,
+        Op::Contains => actual.to_lowercase().contains(&value.to_lowercase()),
+        Op::Lt => ordering() == Some(Ordering::Less),
+        Op::Le => matches!(ordering(), Some(Ordering::Less | Ordering::Equal)),
+        Op::Gt => ordering() == Some(Ordering::Greater),
+        Op::Ge => matches!(ordering(), Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+/// Keeps the projects that meet every filter.
+///
+/// A workspace is kept when it or one of its members meets every filter, so that a match in
+/// a member still shows up in lists that group members under their workspace.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to filter, in listing order.
+/// * `filters` - The conditions; an empty list keeps everything.
+pub fn apply<'a>(mut projects: Vec<&'a ProjectInfo>, filters: &[Filter]) -> Vec<&'a ProjectInfo> {
+    if !filters.is_empty() {
+        projects.retain(|info| std::iter::once(*info).chain(&info.members).any(|project| matches_all(project, filters)));
+    }
+    projects
+}
+
+/// Returns `true` if the filters select a whole project rather than only some of its packages.
+///
+/// A workspace is selected whole when it meets the filters and so does every member; a virtual
+/// workspace, which has no package fields of its own, is selected whole when it meets them
+/// itself. Projects that are not workspaces always count as whole.
+pub fn selects_whole(info: &ProjectInfo, filters: &[Filter]) -> bool {
+    !info.is_workspace()
+        || filters.is_empty()
+        || (matches_all(info, filters) && (info.kind == ProjectKind::VirtualWorkspace || info.members.iter().all(|m| matches_all(m, filters))))
+}
+
+/// Replaces the workspaces that the filters select only in part by the packages they select.
+///
+/// Commands that act on each project, such as `each`, then leave out the members that do not
+/// meet the filters. The root of a workspace with a root package is kept as that package
+/// when it meets them.
+///
+/// # Arguments
+///
+/// * `projects` - The projects kept by `apply`, in order.
+/// * `filters` - The conditions they were selected with.
+pub fn packages<'a>(projects: Vec<&'a ProjectInfo>, filters: &[Filter]) -> Vec<&'a ProjectInfo> {
+    let mut packages = Vec::new();
+    for info in projects {
+        if selects_whole(info, filters) {
+            packages.push(info);
+            continue;
+        }
+        if info.kind == ProjectKind::Workspace && matches_all(info, filters) {
+            packages.push(info);
+        }
+        packages.extend(info.members.iter().filter(|member| matches_all(member, filters)));
+    }
+    packages
+}
+
+/// Returns `true` if a project meets every filter.
+pub fn matches_all(info: &ProjectInfo, filters: &[Filter]) -> bool {
+    filters.iter().all(|filter| filter.matches(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, edition: &str) -> ProjectInfo {
+        ProjectInfo { name: name.into(), edition: Some(edition.into()), ..ProjectInfo::default() }
+    }
+
+    fn workspace(kind: ProjectKind, members: Vec<ProjectInfo>) -> ProjectInfo {
+        let edition = (kind == ProjectKind::Workspace).then(|| "2018".to_string());
+        ProjectInfo { name: "ws".into(), kind, edition, members, ..ProjectInfo::default() }
+    }
+
+    fn names(projects: &[&ProjectInfo]) -> Vec<String> {
+        projects.iter().map(|info| info.name.clone()).collect()
+    }
+
+    #[test]
+    fn partly_selected_workspaces_are_split_into_their_matching_packages() {
+        let filters = [Filter::parse("edition<2021").expect("valid filter")];
+        let virtual_ws = workspace(ProjectKind::VirtualWorkspace, vec![package("old", "2018"), package("new", "2021")]);
+        let rooted_ws = workspace(ProjectKind::Workspace, vec![package("old", "2018"), package("new", "2021")]);
+        let all_old = workspace(ProjectKind::Workspace, vec![package("old", "2018")]);
+        let solo = package("solo", "2015");
+
+        assert!(!selects_whole(&virtual_ws, &filters));
+        assert!(!selects_whole(&rooted_ws, &filters));
+        assert!(selects_whole(&all_old, &filters));
+        assert!(selects_whole(&virtual_ws, &[]));
+
+        let kept = apply(vec![&virtual_ws, &rooted_ws, &all_old, &solo], &filters);
+        assert_eq!(names(&kept), ["ws", "ws", "ws", "solo"]);
+        assert_eq!(names(&packages(kept, &filters)), ["old", "ws", "old", "ws", "solo"]);
+    }
+
+    #[test]
+    fn a_virtual_workspace_selected_by_its_own_fields_stays_whole() {
+        let filters = [Filter::parse("name=ws").expect("valid filter")];
+        let virtual_ws = workspace(ProjectKind::VirtualWorkspace, vec![package("a", "2021")]);
+        assert!(selects_whole(&virtual_ws, &filters));
+        assert_eq!(names(&packages(apply(vec![&virtual_ws], &filters), &filters)), ["ws"]);
+    }
+
+    #[test]
+    fn filter_expressions_parse() {
+        let compare = |field: &str, op: Op, value: &str| Filter::Compare { field: field.into(), op, value: value.into() };
+        assert_eq!(Filter::parse("edition<2021"), Ok(compare("edition", Op::Lt, "2021")));
+        assert_eq!(Filter::parse(" License = MIT "), Ok(compare("license", Op::Eq, "MIT")));
+        assert_eq!(Filter::parse("version>=1.0"), Ok(compare("version", Op::Ge, "1.0")));
+        assert_eq!(Filter::parse("name!=demo"), Ok(compare("name", Op::Ne, "demo")));
+        assert_eq!(Filter::parse("name==demo"), Ok(compare("name", Op::Eq, "demo")));
+        assert_eq!(Filter::parse("description~cli"), Ok(compare("description", Op::Contains, "cli")));
+        assert_eq!(Filter::parse("size>2G"), Ok(compare("size", Op::Gt, "2G")));
+        assert_eq!(Filter::parse("web server"), Ok(Filter::Query("web server".into())));
+
+        assert!(Filter::parse("colour=red").is_err_and(|e| e.contains("Unknown filter field `colour`")));
+        assert!(Filter::parse("size>lots").is_err());
+        assert!(Filter::parse("name!demo").is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        let info = ProjectInfo {
+            name: "Demo".into(),
+            version: Some("1.10.0".into()),
+            edition: Some("2018".into()),
+            keywords: vec!["cli".into(), "tool".into()],
+            description: Some("A command-line tool".into()),
+            ..ProjectInfo::default()
+        };
+        let matches = |text: &str| Filter::parse(text).expect("valid filter").matches(&info);
+        assert!(matches("name=demo"), "equality ignores case");
+        assert!(matches("version>1.9"), "versions compare by component");
+        assert!(!matches("version<1.9"));
+        assert!(matches("edition<2021") && matches("edition<=2018") && !matches("edition>2018"));
+        assert!(matches("keywords=tool") && !matches("keywords=too"), "list fields test single items");
+        assert!(matches("keywords!=web"));
+        assert!(matches("description~COMMAND"));
+        assert!(matches("name>c") && matches("name<e"), "text compares alphabetically");
+        assert!(!matches("license<z") && !matches("license>a"), "unset fields never compare");
+        assert!(matches("license!=MIT"));
+    }
+}
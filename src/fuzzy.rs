@@ -0,0 +1,117 @@
+/// Points for each matched character.
+const MATCH_SCORE: i64 = 16;
+/// Extra points when a matched character directly follows the previous match.
+const CONSECUTIVE_BONUS: i64 = 12;
+/// Extra points when a matched character starts a word, e.g. after `_`, `-`, `/` or a space.
+const BOUNDARY_BONUS: i64 = 8;
+/// Points lost for each unmatched character between two matches.
+const GAP_PENALTY: i64 = 1;
+
+/// The result of a successful fuzzy match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// How well the pattern matched; higher is better.
+    pub score: i64,
+    /// The indices (in characters) of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+/// Matches a pattern against a text as a case-insensitive subsequence.
+///
+/// Every character of the pattern must appear in the text in order, but not necessarily next
+/// to each other, so `mrm` matches `my_rust_manager`. Consecutive matches and matches at the
+/// start of a word score higher than scattered ones. Each occurrence of the pattern's first
+/// character is tried as a starting point and the best-scoring match is kept.
+///
+/// # Arguments
+///
+/// * `pattern` - The characters to look for. Whitespace in the pattern is ignored.
+/// * `text` - The text to search.
+///
+/// # Returns
+///
+/// The best match, or `None` if the text does not contain the pattern as a subsequence.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    let original: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = original.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+
+    let first = *pattern.first()?;
+    (0..lower.len())
+        .filter(|&start| lower[start] == first)
+        .filter_map(|start| match_from(&pattern, &original, &lower, start))
+        .max_by(|a, b| a.score.cmp(&b.score).then_with(|| b.positions.cmp(&a.positions)))
+}
+
+/// Greedily matches the pattern starting at the given text position and scores the result.
+fn match_from(pattern: &[char], original: &[char], lower: &[char], start: usize) -> Option<Match> {
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut next = start;
+    for &wanted in pattern {
+        let found = (next..lower.len()).find(|&i| lower[i] == wanted)?;
+        positions.push(found);
+        next = found + 1;
+    }
+
+    let mut score = 0;
+    for (n, &position) in positions.iter().enumerate() {
+        score += MATCH_SCORE;
+        if is_boundary(original, position) {
+            score += BOUNDARY_BONUS;
+        }
+        if n > 0 {
+            let gap = position - positions[n - 1] - 1;
+            if gap == 0 {
+                score += CONSECUTIVE_BONUS;
+            } else {
+                score -= gap as i64 * GAP_PENALTY;
+            }
+        }
+    }
+    Some(Match { score, positions })
+}
+
+/// Returns `true` if the character at `index` starts a word.
+///
+/// A word starts at the beginning of the text, after a separator such as `_`, `-`, `/`,
+/// `.` or whitespace, and at an uppercase letter following a lowercase one.
+fn is_boundary(text: &[char], index: usize) -> bool {
+    let Some(index_before) = index.checked_sub(1) else {
+        return true;
+    };
+    let (before, current) = (text[index_before], text[index]);
+    matches!(before, '_' | '-' | '/' | '.' | ':') || before.is_whitespace() || (before.is_lowercase() && current.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).unwrap_or_else(|| panic!("{:?} should match {:?}", pattern, text)).score
+    }
+
+    #[test]
+    fn subsequences_match_in_any_case() {
+        assert_eq!(fuzzy_match("mrm", "my_rust_manager").map(|m| m.positions), Some(vec![0, 3, 8]));
+        assert_eq!(fuzzy_match("RUST", "my_rust").map(|m| m.positions), Some(vec![3, 4, 5, 6]));
+        assert_eq!(fuzzy_match("my rust", "my_rust").map(|m| m.positions.len()), Some(6), "whitespace is ignored");
+        assert_eq!(fuzzy_match("tsur", "my_rust"), None, "order matters");
+        assert_eq!(fuzzy_match("rustx", "my_rust"), None);
+        assert_eq!(fuzzy_match("", "my_rust"), None);
+        assert_eq!(fuzzy_match(" ", "my_rust"), None);
+    }
+
+    #[test]
+    fn ranking() {
+        // Consecutive characters beat scattered ones
+        assert!(score("cli", "cli-tools") > score("cli", "cargo-lint"));
+        // Word starts beat the middle of words, including camel case
+        assert!(score("dp", "dev_parse") > score("dp", "devxparse"));
+        assert!(score("fb", "FooBar") > score("fb", "foobar"));
+        // Shorter gaps beat longer ones
+        assert!(score("ab", "a_xb") > score("ab", "a_xxxxb"));
+        // The best starting point is chosen, not the first one
+        assert_eq!(fuzzy_match("ma", "my_manager").map(|m| m.positions), Some(vec![3, 4]));
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The list columns that show Git information, added by `--git`.
+pub const COLUMNS: &[&str] = &["branch", "upstream", "ahead", "behind", "changed", "untracked", "stashes", "last-commit", "author", "subject"];
+
+/// The state of the Git repository a project lives in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitStatus {
+    /// The checked-out branch, or `None` when `HEAD` is detached.
+    pub branch: Option<String>,
+    /// The upstream branch the current branch tracks, e.g. `origin/main`.
+    pub upstream: Option<String>,
+    /// Commits on the branch that are not on its upstream.
+    pub ahead: u32,
+    /// Commits on the upstream that are not on the branch.
+    pub behind: u32,
+    /// Files below the project directory with staged or unstaged changes, including conflicts.
+    pub changed: usize,
+    /// Untracked files and directories below the project directory.
+    pub untracked: usize,
+    /// Entries in the repository's stash.
+    pub stashes: usize,
+    /// The commit `HEAD` points to, or `None` in a repository without commits.
+    pub last_commit: Option<Commit>,
+}
+
+impl GitStatus {
+    /// Returns `true` if the project has changed or untracked files.
+    pub fn is_dirty(&self) -> bool {
+        self.changed > 0 || self.untracked > 0
+    }
+}
+
+/// A commit's date, author and subject line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    /// The commit date.
+    pub time: SystemTime,
+    /// The author's name.
+    pub author: String,
+    /// The first line of the commit message.
+    pub subject: String,
+}
+
+/// Reads the Git status of a project directory, remembering the result for later calls.
+///
+/// Only the local repository is read: ahead and behind are counted against the upstream
+/// branch as last fetched, and nothing is fetched. Changed and untracked files are counted
+/// below the project directory only, so projects sharing one repository each get their own
+/// counts, while the branch and stashes belong to the whole repository.
+///
+/// # Arguments
+///
+/// * `dir` - The project directory.
+///
+/// # Returns
+///
+/// The status, or `None` if the directory is not inside a Git repository or `git` is not installed.
+pub fn status(dir: &Path) -> Option<GitStatus> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<GitStatus>>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(status) = cache.lock().expect("git cache lock poisoned").get(dir) {
+        return status.clone();
+    }
+    let status = read_status(dir);
+    cache.lock().expect("git cache lock poisoned").insert(dir.to_path_buf(), status.clone());
+    status
+}
+
+/// Runs `git status`, `git rev-list` and `git log` to read the status of a project directory.
+fn read_status(dir: &Path) -> Option<GitStatus> {
+    let porcelain = git(dir, &["status", "--porcelain=v2", "--branch", "--", "."])?;
+    let mut status = GitStatus::default();
+    for line in porcelain.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+            status.upstream = Some(upstream.to_string());
+        } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+            for count in counts.split_whitespace() {
+                if let Some(ahead) = count.strip_prefix('+') {
+                    status.ahead = ahead.parse().unwrap_or(0);
+                } else if let Some(behind) = count.strip_prefix('-') {
+                    status.behind = behind.parse().unwrap_or(0);
+                }
+            }
+        } else if line.starts_with("1 ") || line.starts_with("2 ") || line.starts_with("u ") {
+            status.changed += 1;
+        } else if line.starts_with("? ") {
+            status.untracked += 1;
+        }
+    }
+
+    status.stashes = git(dir, &["rev-list", "--walk-reflogs", "--count", "refs/stash"])
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0);
+    status.last_commit = git(dir, &["log", "-1", "--format=%ct%x00%an%x00%s"]).and_then(|log| {
+        let mut fields = log.trim_end_matches('\n').splitn(3, '\0');
+        let seconds: u64 = fields.next()?.parse().ok()?;
+        Some(Commit {
+            time: UNIX_EPOCH + Duration::from_secs(seconds),
+            author: fields.next()?.to_string(),
+            subject: fields.next()?.to_string(),
+        })
+    });
+    Some(status)
+}
+
+/// Runs a read-only `git` command in a directory and returns its output if it succeeded.
+///
+/// `GIT_OPTIONAL_LOCKS=0` keeps `git status` from refreshing the index, so it never
+/// competes for the lock with a Git command the user is running at the same time.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
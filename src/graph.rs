@@ -0,0 +1,477 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use toml::value::Table;
+use toml::Value;
+
+use crate::deps::{DependencyKind, Source};
+use crate::{ProjectInfo, ProjectKind};
+
+/// The `path` dependencies between local projects.
+pub struct Graph<'a> {
+    /// Every package in the graph; edges refer to them by index.
+    pub nodes: Vec<&'a ProjectInfo>,
+    /// The name each node is shown under, in the same order.
+    pub labels: Vec<String>,
+    /// The edges from a package to the local packages it depends on, sorted by index.
+    pub edges: Vec<Edge>,
+    /// The workspaces among the projects, with the name of each and the indices of its
+    /// packages, used to group members in the diagrams.
+    pub workspaces: Vec<(String, Vec<usize>)>,
+    /// Path dependencies pointing at a directory that is not one of the nodes, with the index
+    /// of the package that declares them.
+    pub external: Vec<(usize, String, PathBuf)>,
+}
+
+/// A dependency of one local package on another.
+pub struct Edge {
+    /// The index of the package that declares the dependency.
+    pub from: usize,
+    /// The index of the package it depends on.
+    pub to: usize,
+    /// The kinds of dependency tables it is declared in.
+    pub kinds: Vec<DependencyKind>,
+}
+
+impl Edge {
+    /// Returns `true` unless the dependency is only a dev-dependency.
+    ///
+    /// Cargo allows cycles through dev-dependencies, since tests are built separately, so
+    /// only the other edges count when looking for cycles.
+    pub fn is_build_edge(&self) -> bool {
+        self.kinds.iter().any(|kind| *kind != DependencyKind::Dev)
+    }
+
+    /// The kinds of the edge joined for display, e.g. `normal, dev`.
+    pub fn kinds_text(&self) -> String {
+        self.kinds.iter().map(|kind| kind.to_string()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// A project that depends on the one asked about in `rdeps`, directly or through others.
+pub struct Dependent {
+    /// The index of the dependent package.
+    pub node: usize,
+    /// 1 for a direct dependency, 2 for a dependency of a direct dependent and so on.
+    pub depth: usize,
+    /// The index of the edge to the package it depends on on the way to the one asked about.
+    pub edge: usize,
+}
+
+impl<'a> Graph<'a> {
+    /// Builds the graph of `path` dependencies between projects.
+    ///
+    /// Every package is a node, including workspace members; virtual workspaces have no
+    /// package and only group their members. A dependency becomes an edge when its path is
+    /// the directory of another node, comparing the canonical forms of both, so the caller
+    /// may pass relative or non-canonical project paths. The kinds of all the
+    /// entries between the same two packages are merged into one edge.
+    ///
+    /// # Arguments
+    ///
+    /// * `projects` - The projects to include, each with its workspace members.
+    /// * `label` - Returns the name a package is shown under.
+    pub fn build(projects: &[&'a ProjectInfo], label: impl Fn(&ProjectInfo) -> String) -> Graph<'a> {
+        let mut nodes: Vec<&ProjectInfo> = Vec::new();
+        let mut by_path: HashMap<PathBuf, usize> = HashMap::new();
+        let mut workspaces = Vec::new();
+        for info in projects {
+            let packages = std::iter::once(*info)
+                .filter(|info| info.kind != ProjectKind::VirtualWorkspace)
+                .chain(&info.members);
+            let mut indices = Vec::new();
+            for package in packages {
+                let path = fs::canonicalize(&package.path).unwrap_or_else(|_| package.path.clone());
+                let index = *by_path.entry(path).or_insert_with(|| {
+                    nodes.push(package);
+                    nodes.len() - 1
+                });
+                indices.push(index);
+            }
+            if info.is_workspace() {
+                workspaces.push((label(info), indices));
+            }
+        }
+        let labels = nodes.iter().map(|node| label(node)).collect();
+
+        let mut merged: BTreeMap<(usize, usize), BTreeSet<DependencyKind>> = BTreeMap::new();
+        let mut external = Vec::new();
+        for (from, node) in nodes.iter().enumerate() {
+            for dependency in &node.dependencies {
+                let Source::Path(path) = &dependency.source else {
+                    continue;
+                };
+                let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                match by_path.get(&path) {
+                    Some(&to) => {
+                        merged.entry((from, to)).or_default().insert(dependency.kind);
+                    }
+                    None => external.push((from, dependency.name.clone(), path)),
+                }
+            }
+        }
+        let edges = merged
+            .into_iter()
+            .map(|((from, to), kinds)| Edge { from, to, kinds: kinds.into_iter().collect() })
+            .collect();
+
+        Graph { nodes, labels, edges, workspaces, external }
+    }
+
+    /// Finds the dependency cycles among the normal and build dependencies.
+    ///
+    /// The strongly connected components of the graph are found with Tarjan's algorithm, and
+    /// one cycle is traced through each component that has more than one package or a
+    /// package depending on itself.
+    ///
+    /// # Returns
+    ///
+    /// Each cycle as the indices of its packages in dependency order, starting with the
+    /// lowest index; the first package is not repeated at the end.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let successors = self.build_successors();
+        let mut cycles = Vec::new();
+        for component in strongly_connected(&successors) {
+            let start = component[0];
+            if component.len() == 1 && !successors[start].contains(&start) {
+                continue;
+            }
+            cycles.push(trace_cycle(&successors, &component, start));
+        }
+        cycles.sort();
+        cycles
+    }
+
+    /// Returns the index of the edges that lie on one of the given cycles.
+    fn cycle_edges(&self, cycles: &[Vec<usize>]) -> BTreeSet<usize> {
+        let mut steps = BTreeSet::new();
+        for cycle in cycles {
+            for (position, &node) in cycle.iter().enumerate() {
+                steps.insert((node, cycle[(position + 1) % cycle.len()]));
+            }
+        }
+        self.edges
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| steps.contains(&(edge.from, edge.to)))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Finds every package that depends on one of the given packages, directly or through
+    /// other packages, including through dev-dependencies.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets` - The indices of the packages asked about.
+    ///
+    /// # Returns
+    ///
+    /// The dependents nearest first, each with the package it depends on along the shortest
+    /// chain. The targets themselves are never included.
+    pub fn dependents(&self, targets: &[usize]) -> Vec<Dependent> {
+        let mut found: Vec<Dependent> = Vec::new();
+        let mut seen: BTreeSet<usize> = targets.iter().copied().collect();
+        let mut queue: VecDeque<(usize, usize)> = targets.iter().map(|&target| (target, 0)).collect();
+        while let Some((node, depth)) = queue.pop_front() {
+            for (index, edge) in self.edges.iter().enumerate().filter(|(_, edge)| edge.to == node) {
+                if seen.insert(edge.from) {
+                    found.push(Dependent { node: edge.from, depth: depth + 1, edge: index });
+                    queue.push_back((edge.from, depth + 1));
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns the successors of every node along the edges that are not only dev-dependencies.
+    fn build_successors(&self) -> Vec<Vec<usize>> {
+        let mut successors = vec![Vec::new(); self.nodes.len()];
+        for edge in self.edges.iter().filter(|edge| edge.is_build_edge()) {
+            successors[edge.from].push(edge.to);
+        }
+        successors
+    }
+
+    /// Writes the graph in Graphviz DOT.
+    ///
+    /// Dev-dependencies are dashed, build-dependencies dotted and edges on a cycle red;
+    /// workspace members are drawn inside a box labelled with the workspace.
+    pub fn to_dot(&self) -> String {
+        let cycle_edges = self.cycle_edges(&self.cycles());
+        let mut out = String::from("digraph projects {\n    rankdir=LR;\n    node [shape=box];\n");
+        let mut grouped = BTreeSet::new();
+        for (index, (name, members)) in self.workspaces.iter().enumerate() {
+            writeln!(out, "    subgraph cluster_{} {{\n        label={};", index, dot_string(name)).unwrap();
+            for &member in members.iter().filter(|&&member| grouped.insert(member)) {
+                writeln!(out, "        n{} [label={}];", member, dot_string(&self.node_label(member))).unwrap();
+            }
+            out.push_str("    }\n");
+        }
+        for index in (0..self.nodes.len()).filter(|index| !grouped.contains(index)) {
+            writeln!(out, "    n{} [label={}];", index, dot_string(&self.node_label(index))).unwrap();
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            let mut attributes = Vec::new();
+            if !edge.kinds.contains(&DependencyKind::Normal) {
+                let style = if edge.is_build_edge() { "dotted" } else { "dashed" };
+                attributes.push(format!("style={}", style));
+                attributes.push(format!("label={}", dot_string(&edge.kinds_text())));
+            }
+            if cycle_edges.contains(&index) {
+                attributes.push("color=red".to_string());
+            }
+            let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+            writeln!(out, "    n{} -> n{}{};", edge.from, edge.to, attributes).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes the graph as a Mermaid flowchart, styled like the DOT output.
+    pub fn to_mermaid(&self) -> String {
+        let cycle_edges = self.cycle_edges(&self.cycles());
+        let mut out = String::from("flowchart LR\n");
+        let mut grouped = BTreeSet::new();
+        for (index, (name, members)) in self.workspaces.iter().enumerate() {
+            writeln!(out, "    subgraph w{} [{}]", index, mermaid_string(name)).unwrap();
+            for &member in members.iter().filter(|&&member| grouped.insert(member)) {
+                writeln!(out, "        n{}[{}]", member, mermaid_string(&self.node_label(member))).unwrap();
+            }
+            out.push_str("    end\n");
+        }
+        for index in (0..self.nodes.len()).filter(|index| !grouped.contains(index)) {
+            writeln!(out, "    n{}[{}]", index, mermaid_string(&self.node_label(index))).unwrap();
+        }
+        for edge in &self.edges {
+            if edge.kinds.contains(&DependencyKind::Normal) {
+                writeln!(out, "    n{} --> n{}", edge.from, edge.to).unwrap();
+            } else {
+                writeln!(out, "    n{} -.->|{}| n{}", edge.from, mermaid_string(&edge.kinds_text()), edge.to).unwrap();
+            }
+        }
+        for index in cycle_edges {
+            writeln!(out, "    linkStyle {} stroke:red", index).unwrap();
+        }
+        out
+    }
+
+    /// Builds the machine-readable form of the graph: the `projects` with their index,
+    /// the `dependencies` between them and the `cycles` as lists of project names.
+    pub fn record(&self) -> Value {
+        let projects = self
+            .nodes
+            .iter()
+            .zip(&self.labels)
+            .enumerate()
+            .map(|(index, (node, label))| {
+                let mut table = Table::new();
+                table.insert("id".into(), Value::Integer(index as i64));
+                table.insert("name".into(), Value::String(label.clone()));
+                if let Some(version) = &node.version {
+                    table.insert("version".into(), Value::String(version.clone()));
+                }
+                table.insert("path".into(), Value::String(node.path.display().to_string()));
+                Value::Table(table)
+            })
+            .collect();
+        let cycles = self
+            .cycles()
+            .into_iter()
+            .map(|cycle| Value::Array(cycle.into_iter().map(|node| Value::String(self.labels[node].clone())).collect()))
+            .collect();
+
+        let mut document = Table::new();
+        document.insert("projects".into(), Value::Array(projects));
+        document.insert("dependencies".into(), Value::Array(self.edge_records()));
+        document.insert("cycles".into(), Value::Array(cycles));
+        Value::Table(document)
+    }
+
+    /// Converts the edges into records with the names, ids and paths of both ends.
+    pub fn edge_records(&self) -> Vec<Value> {
+        self.edges
+            .iter()
+            .map(|edge| {
+                let mut table = Table::new();
+                table.insert("from".into(), Value::String(self.labels[edge.from].clone()));
+                table.insert("to".into(), Value::String(self.labels[edge.to].clone()));
+                table.insert("from-id".into(), Value::Integer(edge.from as i64));
+                table.insert("to-id".into(), Value::Integer(edge.to as i64));
+                table.insert("from-path".into(), Value::String(self.nodes[edge.from].path.display().to_string()));
+                table.insert("to-path".into(), Value::String(self.nodes[edge.to].path.display().to_string()));
+                table.insert("kinds".into(), Value::Array(edge.kinds.iter().map(|kind| Value::String(kind.to_string())).collect()));
+                Value::Table(table)
+            })
+            .collect()
+    }
+
+    /// Returns the diagram label of a node: its name and, if known, its version.
+    fn node_label(&self, index: usize) -> String {
+        match &self.nodes[index].version {
+            Some(version) => format!("{} {}", self.labels[index], version),
+            None => self.labels[index].clone(),
+        }
+    }
+}
+
+/// Finds the strongly connected components of a graph with Tarjan's algorithm.
+///
+/// The search is iterative, so deep chains of dependencies cannot overflow the stack.
+///
+/// # Arguments
+///
+/// * `successors` - The successors of each node.
+///
+/// # Returns
+///
+/// Every component, each sorted by node index.
+fn strongly_connected(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let count = successors.len();
+    let mut index = vec![usize::MAX; count];
+    let mut lowlink = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next = 0;
+
+    for root in 0..count {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // Each frame is a node and how many of its successors have been looked at
+        let mut frames = vec![(root, 0)];
+        index[root] = next;
+        lowlink[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(frame) = frames.last_mut() {
+            let node = frame.0;
+            if let Some(&successor) = successors[node].get(frame.1) {
+                frame.1 += 1;
+                if index[successor] == usize::MAX {
+                    index[successor] = next;
+                    lowlink[successor] = next;
+                    next += 1;
+                    stack.push(successor);
+                    on_stack[successor] = true;
+                    frames.push((successor, 0));
+                } else if on_stack[successor] {
+                    lowlink[node] = lowlink[node].min(index[successor]);
+                }
+                continue;
+            }
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[node]);
+            }
+            if lowlink[node] == index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Traces a shortest cycle from `start` back to itself within a strongly connected component.
+fn trace_cycle(successors: &[Vec<usize>], component: &[usize], start: usize) -> Vec<usize> {
+    if successors[start].contains(&start) {
+        return vec![start];
+    }
+    let mut previous: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &successor in &successors[node] {
+            if successor == start {
+                let mut cycle = vec![node];
+                while let Some(&before) = previous.get(cycle.last().expect("cycle is not empty")) {
+                    cycle.push(before);
+                }
+                if *cycle.last().expect("cycle is not empty") != start {
+                    cycle.push(start);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if component.binary_search(&successor).is_ok() && !previous.contains_key(&successor) && successor != start {
+                previous.insert(successor, node);
+                queue.push_back(successor);
+            }
+        }
+    }
+    vec![start]
+}
+
+/// Quotes a string for DOT.
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quotes a string for a Mermaid label, where double quotes are written as an entity.
+fn mermaid_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deps::Dependency;
+
+    /// A package at `/g/<name>` with path dependencies on the given packages.
+    fn package(name: &str, dependencies: &[(&str, DependencyKind)]) -> ProjectInfo {
+        let dependencies = dependencies
+            .iter()
+            .map(|(to, kind)| Dependency {
+                name: to.to_string(),
+                package: None,
+                kind: *kind,
+                source: Source::Path(PathBuf::from("/g").join(to)),
+                req: None,
+                optional: false,
+                default_features: true,
+                features: Vec::new(),
+                target: None,
+                inherited: false,
+            })
+            .collect();
+        ProjectInfo { name: name.into(), path: PathBuf::from("/g").join(name), dependencies, ..ProjectInfo::default() }
+    }
+
+    #[test]
+    fn cycles_ignore_dev_dependencies() {
+        use DependencyKind::{Build, Dev, Normal};
+        let infos = [
+            package("a", &[("b", Normal), ("outside", Normal)]),
+            package("b", &[("c", Normal), ("c", Dev)]),
+            package("c", &[("a", Build), ("d", Dev)]),
+            package("d", &[("c", Normal)]),
+            package("e", &[("e", Normal)]),
+        ];
+        let projects: Vec<&ProjectInfo> = infos.iter().collect();
+        let graph = Graph::build(&projects, |info| info.name.clone());
+
+        let edges: Vec<(usize, usize, String)> = graph.edges.iter().map(|e| (e.from, e.to, e.kinds_text())).collect();
+        assert_eq!(
+            edges,
+            [(0, 1, "normal".into()), (1, 2, "normal, dev".into()), (2, 0, "build".into()), (2, 3, "dev".into()), (3, 2, "normal".into()), (4, 4, "normal".into())]
+        );
+        assert_eq!(graph.external, [(0, "outside".to_string(), PathBuf::from("/g/outside"))]);
+        // `c` and `d` only form a cycle through a dev-dependency
+        assert_eq!(graph.cycles(), [vec![0, 1, 2], vec![4]]);
+
+        let dependents: Vec<(usize, usize)> = graph.dependents(&[2]).iter().map(|d| (d.node, d.depth)).collect();
+        assert_eq!(dependents, [(1, 1), (3, 1), (0, 2)]);
+    }
+}
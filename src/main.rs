@@ -1,29 +1,143 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::io::{self, Write};
-use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Condvar, Mutex};
+use std::thread;
 use toml::Value;
-use ctrlc;
-use dirs;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use output::Format;
+
+mod artifacts;
+mod batch;
+mod cache;
+mod cargo;
+mod config;
+mod deps;
+mod disk;
+mod drift;
+mod filter;
+mod fuzzy;
+mod git;
+mod graph;
+mod outdated;
+mod output;
+mod registry;
+mod search;
+mod sort;
+mod targets;
+#[cfg(feature = "tui")]
+mod tui;
+mod util;
+mod watch;
+mod workspace;
+
+/// Directory names that are never searched for projects.
+const SKIPPED_DIRS: &[&str] = &["target", ".git"];
+
+/// The edition Cargo uses for a package whose manifest has no `edition` field.
+const DEFAULT_EDITION: &str = "2015";
+
+/// The kind of manifest a project was discovered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ProjectKind {
+    /// A single crate described by a `[package]` table.
+    #[default]
+    Package,
+    /// A workspace root described by a `[workspace]` table that also has its own `[package]`.
+    Workspace,
+    /// A virtual workspace root, which has a `[workspace]` table but no `[package]`.
+    VirtualWorkspace,
+}
 
 /// Struct representing information about a Rust project.
 ///
-/// This struct holds the project's name, an optional description, and the path to the project.
-#[derive(Debug)]
+/// This struct holds the project's name, an optional description, the path to the project
+/// and the rest of the metadata from its `[package]` table. Workspace roots also carry the
+/// member crates listed in their `[workspace]` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 struct ProjectInfo {
     /// The name of the project.
     name: String,
     /// An optional description of the project.
     description: Option<String>,
+    /// The package version, if the manifest declares one.
+    version: Option<String>,
+    /// The package's `authors`.
+    authors: Vec<String>,
+    /// The SPDX `license` expression.
+    license: Option<String>,
+    /// The path of a non-standard license file, from `license-file`.
+    license_file: Option<String>,
+    /// The Rust `edition`, if declared; see `ProjectInfo::edition` for the effective value.
+    edition: Option<String>,
+    /// The minimum supported Rust version, from `rust-version`.
+    rust_version: Option<String>,
+    /// The source repository URL.
+    repository: Option<String>,
+    /// The homepage URL.
+    homepage: Option<String>,
+    /// The documentation URL.
+    documentation: Option<String>,
+    /// The path of the README file, unless `readme = false`.
+    readme: Option<String>,
+    /// The package's `keywords`.
+    keywords: Vec<String>,
+    /// The package's crates.io `categories`.
+    categories: Vec<String>,
+    /// The registries the package may be published to: `None` for any registry, an empty
+    /// list for `publish = false`.
+    publish: Option<Vec<String>>,
+    /// The binary `cargo run` runs by default, from `default-run`.
+    default_run: Option<String>,
+    /// The build targets: library, binaries, examples, tests and benches.
+    targets: Vec<targets::Target>,
+    /// The dependencies from `[dependencies]`, `[dev-dependencies]` and `[build-dependencies]`,
+    /// including the platform-specific ones.
+    dependencies: Vec<deps::Dependency>,
     /// The path where the project is located.
     path: PathBuf,
+    /// The scan root the project was found under.
+    root: PathBuf,
+    /// Whether the project is a plain package or a workspace root.
+    kind: ProjectKind,
+    /// The member crates of a workspace, empty for plain packages.
+    members: Vec<ProjectInfo>,
+    /// The names of the fields that were inherited from `[workspace.package]`.
+    inherited: Vec<String>,
+}
+
+impl ProjectInfo {
+    /// Returns `true` if the project is a workspace root, virtual or not.
+    fn is_workspace(&self) -> bool {
+        self.kind != ProjectKind::Package
+    }
+
+    /// Returns the effective edition, which is 2015 when the manifest does not declare one,
+    /// or `None` for a virtual workspace, which has no package to have an edition.
+    fn edition(&self) -> Option<&str> {
+        if self.kind == ProjectKind::VirtualWorkspace {
+            return None;
+        }
+        Some(self.edition.as_deref().unwrap_or(DEFAULT_EDITION))
+    }
+
+    /// Returns a note saying where a field's value came from, for the details view.
+    fn source_note(&self, field: &str) -> &'static str {
+        if self.inherited.iter().any(|f| f == field) {
+            " (inherited from workspace)"
+        } else {
+            ""
+        }
+    }
 }
 
 /// Parses a `Cargo.toml` file and extracts project information.
 ///
 /// This function reads a `Cargo.toml` file from the specified path, extracts the package name
-/// and description (if available), and returns it as a `ProjectInfo` struct.
+/// and description (if available), and returns it as a `ProjectInfo` struct. Manifests with a
+/// `[workspace]` table are returned as workspaces with their member crates filled in; a virtual
+/// workspace without a `[package]` table is named after its directory.
 ///
 /// # Arguments
 ///
@@ -33,80 +147,665 @@ struct ProjectInfo {
 ///
 /// An `Option<ProjectInfo>` with the project name, description, and path.
 fn parse_cargo_toml(path: &Path) -> Option<ProjectInfo> {
-    let cargo_toml = fs::read_to_string(path).ok()?;
-    let parsed: Value = cargo_toml.parse().ok()?;
+    let parsed = read_manifest(path)?;
+    let dir = path.parent()?;
 
-    let package = parsed.get("package")?;
+    let Some(workspace) = parsed.get("workspace") else {
+        let uses_inheritance = parsed
+            .get("package")
+            .and_then(|p| p.as_table())
+            .is_some_and(|p| p.values().any(workspace::is_inherited))
+            || deps::uses_workspace(&parsed);
+        let enclosing = if uses_inheritance { workspace::enclosing_workspace(dir) } else { None };
+        return package_info(&parsed, dir, enclosing.as_ref().map(|(table, root)| (table, root.as_path())));
+    };
+
+    let members = workspace::member_dirs(workspace, dir)
+        .iter()
+        .filter_map(|member| {
+            read_manifest(&member.join("Cargo.toml")).and_then(|m| package_info(&m, member, Some((workspace, dir))))
+        })
+        .collect();
+
+    let mut info = match package_info(&parsed, dir, Some((workspace, dir))) {
+        Some(info) => ProjectInfo { kind: ProjectKind::Workspace, ..info },
+        None => ProjectInfo {
+            name: dir.file_name()?.to_string_lossy().into_owned(),
+            path: dir.to_path_buf(),
+            kind: ProjectKind::VirtualWorkspace,
+            ..ProjectInfo::default()
+        },
+    };
+    info.members = members;
+    Some(info)
+}
+
+/// Reads and parses a `Cargo.toml` file.
+fn read_manifest(path: &Path) -> Option<Value> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// Extracts the `[package]` table of a parsed manifest as a plain package.
+///
+/// Fields written as `{ workspace = true }` are resolved against the enclosing workspace's
+/// `[workspace.package]` table and recorded in `inherited`, and dependencies written that way
+/// against its `[workspace.dependencies]`.
+///
+/// # Arguments
+///
+/// * `manifest` - The parsed `Cargo.toml`.
+/// * `dir` - The directory containing the manifest.
+/// * `workspace` - The `[workspace]` table of the enclosing workspace and its root directory,
+///   if any.
+///
+/// # Returns
+///
+/// `None` if the manifest has no `[package]` table or the package has no name.
+fn package_info(manifest: &Value, dir: &Path, workspace: Option<(&Value, &Path)>) -> Option<ProjectInfo> {
+    let package = manifest.get("package")?;
+    let workspace_package = workspace.and_then(|(table, _)| table.get("package"));
     let name = package.get("name")?.as_str()?.to_string();
-    let description = package.get("description").and_then(|d| d.as_str()).map(|d| d.to_string());
+    let mut inherited = Vec::new();
+
+    let mut field = |key: &str| {
+        let (value, from_workspace) = workspace::resolve_field(package, key, workspace_package)?;
+        if from_workspace {
+            inherited.push(key.to_string());
+        }
+        Some(value)
+    };
+    let mut string_field = |key: &str| field(key).and_then(|v| v.as_str()).map(String::from);
+    let description = string_field("description");
+    let version = string_field("version");
+    let license = string_field("license");
+    let license_file = string_field("license-file");
+    let edition = string_field("edition");
+    let rust_version = string_field("rust-version");
+    let repository = string_field("repository");
+    let homepage = string_field("homepage");
+    let documentation = string_field("documentation");
+    let readme = string_field("readme");
+    let default_run = string_field("default-run");
+    let authors = field("authors").map(string_list).unwrap_or_default();
+    let keywords = field("keywords").map(string_list).unwrap_or_default();
+    let categories = field("categories").map(string_list).unwrap_or_default();
+    let publish = field("publish").and_then(|v| match v {
+        Value::Boolean(true) => None,
+        Value::Boolean(false) => Some(Vec::new()),
+        registries => Some(string_list(registries)),
+    });
 
     Some(ProjectInfo {
         name,
         description,
-        path: path.parent()?.to_path_buf(),
+        version,
+        authors,
+        license,
+        license_file,
+        edition,
+        rust_version,
+        repository,
+        homepage,
+        documentation,
+        readme,
+        keywords,
+        categories,
+        publish,
+        default_run,
+        targets: targets::discover(package, manifest, dir),
+        dependencies: deps::parse(manifest, dir, workspace),
+        path: dir.to_path_buf(),
+        kind: ProjectKind::Package,
+        inherited,
+        ..ProjectInfo::default()
+    })
+}
+
+/// Reads a TOML array of strings, skipping elements that are not strings.
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| items.iter().filter_map(|i| i.as_str()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The discovered projects, keyed by the canonical path of each project directory.
+///
+/// Keying by path rather than by package name keeps every checkout of a crate, even when
+/// several of them share the same package name.
+type ProjectIndex = BTreeMap<PathBuf, ProjectInfo>;
+
+/// Options controlling how the project tree is walked.
+#[derive(Debug, Clone, Default)]
+struct ScanOptions {
+    /// Maximum number of directory levels to descend below the root, or `None` for no limit.
+    max_depth: Option<usize>,
+    /// Keep descending into a directory after a `Cargo.toml` has been found in it.
+    nested: bool,
+    /// Also search hidden directories (names starting with `.`).
+    include_hidden: bool,
+    /// Glob patterns for directories that are never searched.
+    ///
+    /// Patterns without a `/` are matched against the directory name, others against the
+    /// directory's path relative to the scan root.
+    exclude: Vec<String>,
+    /// How many threads read directories and parse manifests at once.
+    jobs: usize,
+}
+
+/// Returns `true` if the walker should not descend into the given directory.
+///
+/// `target/` and `.git/` are always skipped; other hidden directories are skipped
+/// unless `include_hidden` is set in the options, and directories matching one of the
+/// `exclude` patterns are skipped as well.
+///
+/// # Arguments
+///
+/// * `dir` - The directory being considered.
+/// * `root` - The scan root that `dir` was found under.
+/// * `options` - The options controlling which directories are skipped.
+fn is_skipped_dir(dir: &Path, root: &Path, options: &ScanOptions) -> bool {
+    let name = dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    if SKIPPED_DIRS.contains(&name.as_ref()) || (!options.include_hidden && name.starts_with('.')) {
+        return true;
+    }
+
+    let relative = dir.strip_prefix(root).unwrap_or(dir).to_string_lossy();
+    options.exclude.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.contains('/') {
+            workspace::glob_match(pattern, &relative)
+        } else {
+            workspace::glob_match(pattern, &name)
+        }
     })
 }
 
 /// Recursively searches for Rust projects in the specified directory.
 ///
-/// This function looks for directories containing `Cargo.toml` files within
-/// the given root directory and returns a map of project names to their respective `ProjectInfo`.
+/// This function walks the directory tree below the given root, looking for directories
+/// containing `Cargo.toml` files, and returns an index of the projects it finds. Descending
+/// stops at the first `Cargo.toml` on each branch unless `options.nested` is set, and never
+/// goes deeper than `options.max_depth`. Symbolic links to directories are followed; a
+/// directory reached along several routes counts as being at the least depth of any of
+/// them, and links back up the tree cannot loop.
+///
+/// Directories are read and manifests parsed by `options.jobs` threads sharing a queue of
+/// directories still to visit. The index is ordered by path and warnings are printed sorted
+/// once the walk is done, so the result does not depend on which thread got to what first.
 ///
 /// # Arguments
 ///
 /// * `root` - The root directory to search for Rust projects.
+/// * `options` - The options controlling depth, parallelism and which directories are skipped.
+/// * `cache` - The manifests parsed by earlier runs, which unchanged manifests are read from.
+///
+/// # Returns
+///
+/// A `ProjectIndex` mapping the canonical path of each project to its `ProjectInfo`.
+fn find_projects(root: &Path, options: &ScanOptions, cache: &cache::Cache) -> (ProjectIndex, Vec<PathBuf>) {
+    if !root.is_dir() {
+        eprintln!("Could not read directory: {:?}", root);
+        return (ProjectIndex::new(), Vec::new());
+    }
+    walk(root, root, 0, options, cache)
+}
+
+/// Walks the directory tree below `start`, which is `depth` levels below the scan root.
+///
+/// See `find_projects`, which walks a whole scan root; `watch` also uses this to search
+/// again below a directory that changed.
 ///
 /// # Returns
 ///
-/// A `BTreeMap` where the keys are project names and the values are `ProjectInfo` structs.
-fn find_projects(root: &Path) -> BTreeMap<String, ProjectInfo> {
-    let mut projects = BTreeMap::new();
-    if let Ok(entries) = fs::read_dir(root) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-
-            if path.is_dir() {
-                let cargo_toml_path = path.join("Cargo.toml");
-                if cargo_toml_path.exists() {
-                    if let Some(info) = parse_cargo_toml(&cargo_toml_path) {
-                        projects.insert(info.name.clone(), info);
+/// The projects found, and the canonical path of every directory that was visited.
+fn walk(root: &Path, start: &Path, depth: usize, options: &ScanOptions, cache: &cache::Cache) -> (ProjectIndex, Vec<PathBuf>) {
+    let canonical_root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+
+    // The directories waiting to be visited, and how many are queued or being visited
+    let queue = Mutex::new((vec![(start.to_path_buf(), depth)], 1usize));
+    let ready = Condvar::new();
+    let projects = Mutex::new(ProjectIndex::new());
+    // The canonical path of every directory visited, and the least depth it was reached at
+    let visited: Mutex<BTreeMap<PathBuf, usize>> = Mutex::new(BTreeMap::new());
+    let unreadable = Mutex::new(BTreeSet::new());
+
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.max(1) {
+            scope.spawn(|| loop {
+                let (dir, depth) = {
+                    let mut state = queue.lock().expect("walk queue lock poisoned");
+                    loop {
+                        if let Some(next) = state.0.pop() {
+                            break next;
+                        }
+                        if state.1 == 0 {
+                            return;
+                        }
+                        state = ready.wait(state).expect("walk queue lock poisoned");
                     }
+                };
+
+                // Symbolic links are followed, so a directory can be reached more than once, or
+                // through a link back to one of its parents. It is only visited again when reached
+                // at a lesser depth, which may see further below it, so that the result does not
+                // depend on which route a thread took first.
+                let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+                let shallower = {
+                    let mut visited = visited.lock().expect("walk lock poisoned");
+                    let shallower = visited.get(&canonical).is_none_or(|&seen| depth < seen);
+                    if shallower {
+                        visited.insert(canonical.clone(), depth);
+                    }
+                    shallower
+                };
+                let visit = if shallower {
+                    visit_dir(root, &dir, depth, options, cache)
+                } else {
+                    Visit { project: None, subdirs: Vec::new(), readable: true }
+                };
+                if let Some(mut info) = visit.project {
+                    info.root = canonical_root.clone();
+                    for member in &mut info.members {
+                        member.root = info.root.clone();
+                    }
+                    projects.lock().expect("project index lock poisoned").insert(info.path.clone(), info);
+                }
+                if !visit.readable {
+                    unreadable.lock().expect("warning lock poisoned").insert(canonical);
+                }
+
+                let mut state = queue.lock().expect("walk queue lock poisoned");
+                state.1 += visit.subdirs.len();
+                state.1 -= 1;
+                // Popping from the end takes the subdirectories in order, depth first
+                state.0.extend(visit.subdirs.into_iter().rev().map(|subdir| (subdir, depth + 1)));
+                ready.notify_all();
+            });
+        }
+    });
+
+    for dir in unreadable.into_inner().expect("warning lock poisoned") {
+        eprintln!("Could not read directory: {:?}", dir);
+    }
+    let visited = visited.into_inner().expect("walk lock poisoned").into_keys().collect();
+    (projects.into_inner().expect("project index lock poisoned"), visited)
+}
+
+/// What visiting a single directory during the project walk found.
+struct Visit {
+    /// The project whose `Cargo.toml` is in the directory, if any.
+    project: Option<ProjectInfo>,
+    /// The subdirectories still to be searched, sorted by name.
+    subdirs: Vec<PathBuf>,
+    /// Whether the directory's entries could be listed.
+    readable: bool,
+}
+
+/// Visits a single directory during the project walk.
+///
+/// # Arguments
+///
+/// * `root` - The scan root the walk started from.
+/// * `dir` - The directory being visited.
+/// * `depth` - How many levels `dir` is below the scan root.
+/// * `options` - The options controlling depth and which directories are skipped.
+/// * `cache` - The manifests parsed by earlier runs.
+///
+/// # Returns
+///
+/// The project found in the directory and the subdirectories to search next.
+fn visit_dir(root: &Path, dir: &Path, depth: usize, options: &ScanOptions, cache: &cache::Cache) -> Visit {
+    let mut visit = Visit { project: None, subdirs: Vec::new(), readable: true };
+    let cargo_toml_path = dir.join("Cargo.toml");
+    if cargo_toml_path.is_file() {
+        // Parsing from the canonical directory makes member and `path` dependency paths canonical too
+        let canonical_dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if let Some(mut info) = cache.project(&canonical_dir.join("Cargo.toml"), parse_cargo_toml) {
+            info.path = canonical_dir;
+            for member in &mut info.members {
+                if let Ok(path) = fs::canonicalize(&member.path) {
+                    member.path = path;
                 }
             }
+            visit.project = Some(info);
+        }
+        if !options.nested {
+            return visit;
+        }
+    }
+
+    if options.max_depth.is_some_and(|max| depth >= max) {
+        return visit;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            visit.readable = false;
+            return visit;
+        }
+    };
+
+    visit.subdirs = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter(|path| !is_skipped_dir(path, root, options))
+        .collect();
+    visit.subdirs.sort();
+    visit
+}
+
+/// Returns the projects of an index in listing order: by name, then by path.
+fn listed_projects(projects: &ProjectIndex) -> Vec<&ProjectInfo> {
+    let mut listed: Vec<&ProjectInfo> = projects.values().collect();
+    listed.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+    listed
+}
+
+/// Which projects are listed and in which order, from `--filter`, `--sort` and the related options.
+struct Selection {
+    /// The conditions every listed project must meet.
+    filters: Vec<filter::Filter>,
+    /// The sort order, from `--sort` or the `sort` setting.
+    sort: sort::SortKey,
+    /// Whether the sort order was given with `--sort`, which also reorders `find` results.
+    explicit_sort: bool,
+    /// Whether the sort order is reversed.
+    reverse: bool,
+}
+
+/// Returns the projects of an index that meet the selection's filters, in its sort order.
+///
+/// This is what the list, `each`, `disk` and `clean` work on.
+fn selected_projects<'a>(projects: &'a ProjectIndex, selection: &Selection) -> Vec<&'a ProjectInfo> {
+    let mut selected = filter::apply(listed_projects(projects), &selection.filters);
+    sort::sort(&mut selected, selection.sort, selection.reverse);
+    selected
+}
+
+/// Searches every project and workspace member for `find`, keeping the results that pass
+/// the filters, in the order chosen with `--sort` and `--reverse` or best match first.
+fn find_matches<'a>(projects: &'a ProjectIndex, query: &str, selection: &Selection) -> Vec<search::Hit<'a>> {
+    let mut hits = search::search(&all_projects(projects), query);
+    hits.retain(|hit| filter::matches_all(hit.info, &selection.filters));
+    if selection.explicit_sort {
+        let mut order: Vec<&ProjectInfo> = hits.iter().map(|hit| hit.info).collect();
+        sort::sort(&mut order, selection.sort, selection.reverse);
+        hits.sort_by_key(|hit| order.iter().position(|info| std::ptr::eq(*info, hit.info)));
+    } else if selection.reverse {
+        hits.reverse();
+    }
+    hits
+}
+
+/// Returns the names shared by more than one of the given projects.
+fn duplicate_names<'a>(projects: impl IntoIterator<Item = &'a ProjectInfo>) -> BTreeSet<&'a str> {
+    let mut seen = BTreeSet::new();
+    let mut duplicates = BTreeSet::new();
+    for info in projects {
+        if !seen.insert(info.name.as_str()) {
+            duplicates.insert(info.name.as_str());
+        }
+    }
+    duplicates
+}
+
+/// Returns the name a project is listed under.
+///
+/// Projects whose name is shared with another listed project get a disambiguator with their
+/// directory relative to the scan root, and the root itself, e.g. `foo (foo-fork in ~/rust)`.
+///
+/// # Arguments
+///
+/// * `info` - The project being listed.
+/// * `duplicates` - The names shared by more than one listed project.
+fn display_name(info: &ProjectInfo, duplicates: &BTreeSet<&str>) -> String {
+    if !duplicates.contains(info.name.as_str()) {
+        return info.name.clone();
+    }
+    match info.path.strip_prefix(&info.root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => {
+            format!("{} ({} in {})", info.name, relative.display(), info.root.display())
+        }
+        _ => format!("{} ({})", info.name, info.path.display()),
+    }
+}
+
+/// Returns every project in the index together with the members of each workspace.
+fn all_projects(projects: &ProjectIndex) -> Vec<&ProjectInfo> {
+    with_members(projects.values())
+}
+
+/// Returns the given projects, each followed by its workspace members.
+///
+/// A crate reached both as a workspace member and on its own (with `--nested`) is only
+/// returned once.
+fn with_members<'a>(projects: impl IntoIterator<Item = &'a ProjectInfo>) -> Vec<&'a ProjectInfo> {
+    let mut all: Vec<&ProjectInfo> = Vec::new();
+    for info in projects {
+        for project in std::iter::once(info).chain(&info.members) {
+            if !all.iter().any(|seen| seen.path == project.path) {
+                all.push(project);
+            }
+        }
+    }
+    all
+}
+
+/// Finds a single project by name or by path, including workspace members.
+///
+/// # Arguments
+///
+/// * `projects` - The index of discovered projects.
+/// * `query` - A package name, or the path of a project directory.
+///
+/// # Returns
+///
+/// The matching project, or a message explaining why no single project matched.
+fn resolve_project<'a>(projects: &'a ProjectIndex, query: &str) -> Result<&'a ProjectInfo, String> {
+    let all = all_projects(projects);
+
+    if Path::new(query).is_dir() {
+        let path = fs::canonicalize(query).unwrap_or_else(|_| PathBuf::from(query));
+        return all
+            .into_iter()
+            .find(|info| info.path == path)
+            .ok_or_else(|| format!("No project found at {}", path.display()));
+    }
+
+    let matching: Vec<&ProjectInfo> = all.into_iter().filter(|info| info.name == query).collect();
+    match matching.as_slice() {
+        [] => Err(format!("No project named `{}` found.", query)),
+        [info] => Ok(info),
+        _ => {
+            let paths: Vec<String> = matching.iter().map(|info| format!("  {}", info.path.display())).collect();
+            Err(format!("Several projects are named `{}`; use a path instead:\n{}", query, paths.join("\n")))
+        }
+    }
+}
+
+/// Displays every package name that appears more than once, with the paths and versions.
+///
+/// Workspace members are included, so a member crate that is also checked out on its own
+/// shows up in the report. A crate reached both as a member and on its own is counted once.
+/// Machine-readable formats get one record per copy, with `name`, `copies`, `version` (if
+/// set) and `path`.
+///
+/// # Arguments
+///
+/// * `projects` - The index of discovered projects.
+/// * `format` - The output format.
+fn display_duplicates(projects: &ProjectIndex, format: Format) {
+    let mut by_name: BTreeMap<&str, Vec<&ProjectInfo>> = BTreeMap::new();
+    for info in all_projects(projects) {
+        if info.kind != ProjectKind::VirtualWorkspace {
+            by_name.entry(&info.name).or_default().push(info);
+        }
+    }
+    by_name.retain(|_, copies| copies.len() > 1);
+
+    if format != Format::Text {
+        let mut records = Vec::new();
+        for (name, copies) in &by_name {
+            for copy in copies {
+                let mut record = toml::value::Table::new();
+                record.insert("name".into(), Value::String(name.to_string()));
+                record.insert("copies".into(), Value::Integer(copies.len() as i64));
+                if let Some(version) = &copy.version {
+                    record.insert("version".into(), Value::String(version.clone()));
+                }
+                record.insert("path".into(), Value::String(copy.path.display().to_string()));
+                records.push(Value::Table(record));
+            }
+        }
+        output::print_records(records, "duplicate", &["name", "copies", "version", "path"], format);
+        return;
+    }
+    if by_name.is_empty() {
+        println!("No duplicate package names found.");
+        return;
+    }
+
+    for (name, copies) in by_name {
+        println!("{} ({} copies)", name, copies.len());
+        for copy in copies {
+            println!("  {:<12} {}", copy.version.as_deref().unwrap_or("unknown"), copy.path.display());
         }
-    } else {
-        eprintln!("Could not read directory: {:?}", root);
     }
-    projects
 }
 
 /// Displays a list of found Rust projects and allows selection for more details.
 ///
-/// This function lists all the projects found in the specified directory, displaying their
-/// index, name, and description. The user can then select a project by entering its index
-/// to view more detailed information.
+/// This function lists the given projects, displaying their index, name, and description.
+/// When running interactively, the user can then select a project by entering its index
+/// to view more detailed information. When built with the `tui` feature and attached to a
+/// terminal, the interactive list is replaced by the full-screen browser.
 ///
 /// # Arguments
 ///
-/// * `projects` - A reference to a map of project names and their respective `ProjectInfo`.
-fn display_projects(projects: &BTreeMap<String, ProjectInfo>) {
-    if projects.is_empty() {
+/// * `listed` - The projects to list, in listing order.
+/// * `columns` - The columns to show as a table, or empty for the default layout.
+/// * `interactive` - Whether to show the selection prompt after the list.
+/// * `live` - Starts following the filesystem, so the full-screen browser stays up to date.
+fn display_projects<'a>(listed: &[&ProjectInfo], columns: &[String], interactive: bool, live: impl FnOnce() -> Option<watch::LiveList<'a>>) {
+    #[cfg(feature = "tui")]
+    if interactive && !listed.is_empty() && tui::is_available() {
+        tui::run(listed, "", live());
+        return;
+    }
+    #[cfg(not(feature = "tui"))]
+    let _ = live;
+
+    if listed.is_empty() {
         println!("No Rust projects found.");
         return;
     }
 
-    for (index, (name, info)) in projects.iter().enumerate() {
-        println!("{}. {} - {}", index + 1, name, info.description.as_deref().unwrap_or("No description"));
+    print_list(listed, columns);
+    if interactive {
+        select_project(listed, columns);
     }
+}
 
-    println!("Enter the number of the project to view details, or 'q' to quit:");
+/// Prints the numbered project list, with workspace members grouped under each workspace.
+///
+/// When columns are given, the list is printed as a table of those columns instead.
+fn print_list(listed: &[&ProjectInfo], columns: &[String]) {
+    if !columns.is_empty() {
+        output::print_table(listed, columns);
+        return;
+    }
+
+    let duplicates = duplicate_names(listed.iter().copied());
+    for (index, info) in listed.iter().enumerate() {
+        let label = if info.is_workspace() { " [workspace]" } else { "" };
+        println!("{}. {}{} - {}", index + 1, display_name(info, &duplicates), label, info.description.as_deref().unwrap_or("No description"));
+        for member in &info.members {
+            println!("     - {} - {}", member.name, member.description.as_deref().unwrap_or("No description"));
+        }
+    }
+}
+
+/// Displays projects ranked by a fuzzy search and allows selection for more details.
+///
+/// # Arguments
+///
+/// * `hits` - The search results, best match first.
+/// * `query` - The query the results were found with, used to pre-fill the full-screen browser.
+/// * `interactive` - Whether to show the selection prompt after the list.
+/// * `live` - Starts following the filesystem, so the full-screen browser stays up to date.
+fn display_matches<'a>(hits: &[search::Hit], query: &str, interactive: bool, live: impl FnOnce() -> Option<watch::LiveList<'a>>) {
+    let listed: Vec<&ProjectInfo> = hits.iter().map(|hit| hit.info).collect();
+
+    #[cfg(feature = "tui")]
+    if interactive && !listed.is_empty() && tui::is_available() {
+        tui::run(&listed, query, live());
+        return;
+    }
+    #[cfg(not(feature = "tui"))]
+    let _ = (query, live);
+
+    if hits.is_empty() {
+        println!("No matching projects found.");
+        return;
+    }
+
+    print_matches(hits);
+    if interactive {
+        select_project(&listed, &[]);
+    }
+}
+
+/// Prints numbered search results with the matched characters highlighted.
+///
+/// Matches in the name or description are highlighted in place; matches in a keyword,
+/// category or path segment are shown after the description.
+fn print_matches(hits: &[search::Hit]) {
+    let enabled = search::use_highlight();
+    let duplicates = duplicate_names(hits.iter().map(|hit| hit.info));
+
+    for (index, hit) in hits.iter().enumerate() {
+        let info = hit.info;
+        let name = display_name(info, &duplicates);
+        let description = info.description.as_deref().unwrap_or("No description");
+        let (name, description, extra) = match hit.field {
+            search::Field::Name => (search::highlight(&name, &hit.positions, enabled), description.to_string(), String::new()),
+            search::Field::Description => (name, search::highlight(description, &hit.positions, enabled), String::new()),
+            field => {
+                let extra = format!("  ({}: {})", field.label(), search::highlight(&hit.text, &hit.positions, enabled));
+                (name, description.to_string(), extra)
+            }
+        };
+        println!("{}. {} - {}{}", index + 1, name, description, extra);
+    }
+}
+
+/// Prompts for project numbers and shows the details of each selected project until `q`.
+///
+/// Entering `/query` replaces the list with the projects (including workspace members) that
+/// match the query, best match first, and the numbers then refer to that list. A lone `/`
+/// goes back to the full list. Entering a cargo command and a number, e.g. `test 3`, runs
+/// `cargo test` in that project's directory; any further words are passed on to cargo.
+///
+/// # Arguments
+///
+/// * `listed` - The projects that were listed, in listing order.
+/// * `columns` - The columns the list was shown with, for showing it again.
+fn select_project(listed: &[&ProjectInfo], columns: &[String]) {
+    let mut current: Vec<&ProjectInfo> = listed.to_vec();
+    println!("Enter the number of the project to view details, '/text' to search, or 'q' to quit.");
+    println!("To run cargo on a project, enter the command and the number, e.g. 'build 2' or 'run 2 --release'.");
 
     loop {
         print!("> ");
         io::stdout().flush().expect("Failed to flush stdout");
 
         let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read input");
+        if io::stdin().read_line(&mut input).expect("Failed to read input") == 0 {
+            break;
+        }
         let input = input.trim();
 
         if input.to_lowercase() == "q" {
@@ -114,75 +813,1257 @@ fn display_projects(projects: &BTreeMap<String, ProjectInfo>) {
             break;
         }
 
+        if let Some(query) = input.strip_prefix('/') {
+            if query.trim().is_empty() {
+                current = listed.to_vec();
+                print_list(&current, columns);
+            } else {
+                let hits = search::search(&with_members(listed.iter().copied()), query);
+                if hits.is_empty() {
+                    println!("No matching projects found.");
+                    continue;
+                }
+                print_matches(&hits);
+                current = hits.iter().map(|hit| hit.info).collect();
+            }
+            continue;
+        }
+
+        let mut words = input.split_whitespace();
+        if let Some(command) = words.next().filter(|word| cargo::is_command(word)) {
+            let selected = words.next().and_then(|n| n.parse::<usize>().ok()).and_then(|n| n.checked_sub(1)).and_then(|i| current.get(i));
+            match selected {
+                Some(info) => {
+                    let args: Vec<String> = words.map(str::to_string).collect();
+                    let code = run_cargo(info, command, &args);
+                    println!("`cargo {}` exited with code {}.", command, code);
+                }
+                None => println!("Please enter the command followed by a valid project number, e.g. '{} 1'.", command),
+            }
+            continue;
+        }
+
         if let Ok(index) = input.parse::<usize>() {
-            if let Some((_, info)) = projects.iter().nth(index - 1) {
+            if let Some(info) = index.checked_sub(1).and_then(|i| current.get(i)) {
                 display_project_details(info);
             } else {
                 println!("Invalid selection. Please enter a valid project number.");
             }
         } else {
-            println!("Please enter a valid number or 'q' to quit.");
+            println!("Please enter a valid number, a cargo command and a number, '/text' to search, or 'q' to quit.");
         }
     }
 }
 
 /// Displays detailed information about a specific Rust project.
 ///
-/// This function prints the project name, description (if available), and path.
-/// It also provides the location where the compiled project can be run.
+/// This function prints the lines built by `project_details` under a heading.
 ///
 /// # Arguments
 ///
 /// * `info` - A reference to the `ProjectInfo` struct for the selected project.
 fn display_project_details(info: &ProjectInfo) {
     println!("\nProject Details:");
-    println!("Project Name: {}", info.name);
-    println!("Description: {}", info.description.as_deref().unwrap_or("No description"));
-    println!("Path: {:?}", info.path);
-    println!("You can run this project from: {:?}", info.path.join("target/release").to_str());
+    for line in project_details(info) {
+        println!("{}", line);
+    }
 }
 
-/// Main function to handle the execution of the program.
+/// Displays the dependencies of a project for `deps`.
 ///
-/// This function sets up the argument parsing, handles Ctrl+C interrupts, and
-/// defaults to listing projects if no arguments are passed. The available arguments are:
-/// - `--help`: Displays the help manual.
-/// - `--list`: Lists all available projects in the user's Rust projects directory.
-fn main() {
-    // Handle Ctrl+C to exit the program
-    ctrlc::set_handler(move || {
-        println!("\nProgram interrupted. Exiting...");
-        std::process::exit(0);
-    }).expect("Error setting Ctrl+C handler");
+/// A workspace lists the dependencies of its root package, if it has one, followed by those
+/// of each member crate.
+///
+/// # Arguments
+///
+/// * `info` - The project whose dependencies to show.
+fn display_dependencies(info: &ProjectInfo) {
+    let packages = std::iter::once(info)
+        .filter(|info| info.kind != ProjectKind::VirtualWorkspace)
+        .chain(&info.members);
+    for (index, package) in packages.enumerate() {
+        if index > 0 {
+            println!();
+        }
+        match package.dependencies.len() {
+            0 => println!("{} has no dependencies.", package.name),
+            count => {
+                println!("Dependencies of {} ({}):", package.name, count);
+                for line in deps::lines(&package.dependencies, "  ") {
+                    println!("{}", line);
+                }
+            }
+        }
+    }
+}
+
+/// Converts the dependencies of a project, and of its members for a workspace, into records
+/// for machine-readable output, each with the name of the package that declares it.
+fn dependency_records(info: &ProjectInfo) -> Vec<Value> {
+    std::iter::once(info)
+        .chain(&info.members)
+        .flat_map(|package| {
+            package.dependencies.iter().map(|dependency| {
+                let mut record = deps::record(dependency);
+                if let Value::Table(table) = &mut record {
+                    table.insert("project".into(), Value::String(package.name.clone()));
+                }
+                record
+            })
+        })
+        .collect()
+}
+
+/// Displays the `path` dependencies between local projects for `graph`.
+///
+/// Every package with local dependencies is listed with the packages it depends on, followed
+/// by the cycles among them and the path dependencies that lead outside the scanned projects.
+fn display_graph(graph: &graph::Graph) {
+    println!("{} local dependencies between {} packages.", graph.edges.len(), graph.nodes.len());
+    let mut previous = None;
+    for edge in &graph.edges {
+        if previous != Some(edge.from) {
+            println!("  {}", graph.labels[edge.from]);
+            previous = Some(edge.from);
+        }
+        if edge.kinds == [deps::DependencyKind::Normal] {
+            println!("    -> {}", graph.labels[edge.to]);
+        } else {
+            println!("    -> {} ({})", graph.labels[edge.to], edge.kinds_text());
+        }
+    }
+
+    let cycles = graph.cycles();
+    if cycles.is_empty() {
+        println!("No dependency cycles.");
+    } else {
+        println!("Dependency cycles:");
+        for cycle in &cycles {
+            let names: Vec<&str> = cycle.iter().chain(cycle.first()).map(|&node| graph.labels[node].as_str()).collect();
+            println!("  {}", names.join(" -> "));
+        }
+    }
+
+    if !graph.external.is_empty() {
+        println!("Path dependencies outside the scanned projects:");
+        for (from, name, path) in &graph.external {
+            println!("  {} -> {} ({})", graph.labels[*from], name, path.display());
+        }
+    }
+}
 
-    let matches = Command::new("My Rust Manager")
+/// Displays the local projects that depend on a project for `rdeps`.
+///
+/// # Arguments
+///
+/// * `graph` - The dependency graph of every scanned project.
+/// * `name` - The name of the project asked about.
+/// * `dependents` - The dependents found by `graph::Graph::dependents`, nearest first.
+fn display_dependents(graph: &graph::Graph, name: &str, dependents: &[graph::Dependent]) {
+    if dependents.is_empty() {
+        println!("No local project depends on {}.", name);
+        return;
+    }
+    println!("Projects depending on {} ({}):", name, dependents.len());
+    let width = dependents.iter().map(|d| graph.labels[d.node].chars().count()).max().unwrap_or(0);
+    for dependent in dependents {
+        let edge = &graph.edges[dependent.edge];
+        let how = if dependent.depth == 1 {
+            edge.kinds_text()
+        } else {
+            format!("through {} ({})", graph.labels[edge.to], edge.kinds_text())
+        };
+        println!("  {:<width$}  {}", graph.labels[dependent.node], how);
+    }
+}
+
+/// Builds the lines of the details view of a project.
+///
+/// The lines hold the project name, description (if available), path and the rest of the
+/// package metadata, followed by every build target. They also list the executables that have
+/// actually been built, with their profile, size and build time, and flag the ones that are
+/// older than the sources. If nothing has been built yet, the paths a release build would
+/// produce are shown instead. The dependencies are listed before them and the Git status of
+/// the project comes next.
+/// For a workspace, every member crate is listed with its own description and path.
+///
+/// # Arguments
+///
+/// * `info` - A reference to the `ProjectInfo` struct for the selected project.
+///
+/// # Returns
+///
+/// The lines of the details view, without trailing newlines.
+fn project_details(info: &ProjectInfo) -> Vec<String> {
+    let text = |value: &Option<String>, field: &str| match value {
+        Some(value) => format!("{}{}", value, info.source_note(field)),
+        None => "Not set".to_string(),
+    };
+    let list = |values: &[String], field: &str| {
+        if values.is_empty() {
+            "None".to_string()
+        } else {
+            format!("{}{}", values.join(", "), info.source_note(field))
+        }
+    };
+    let edition = match (&info.edition, info.edition()) {
+        (Some(edition), _) => format!("{}{}", edition, info.source_note("edition")),
+        (None, Some(edition)) => format!("{} (default)", edition),
+        (None, None) => "None".to_string(),
+    };
+    let publish = match &info.publish {
+        None => "Yes".to_string(),
+        Some(registries) if registries.is_empty() => "No".to_string(),
+        Some(registries) => format!("Only to {}", registries.join(", ")),
+    };
+
+    let mut lines = vec![
+        format!("Project Name: {}", info.name),
+        format!("Description: {}{}", info.description.as_deref().unwrap_or("No description"), info.source_note("description")),
+        format!("Version: {}{}", info.version.as_deref().unwrap_or("Unknown"), info.source_note("version")),
+        format!("Authors: {}", list(&info.authors, "authors")),
+        format!("License: {}", text(&info.license, "license")),
+        format!("License File: {}", text(&info.license_file, "license-file")),
+        format!("Edition: {}", edition),
+        format!("Rust Version: {}", text(&info.rust_version, "rust-version")),
+        format!("Repository: {}", text(&info.repository, "repository")),
+        format!("Homepage: {}", text(&info.homepage, "homepage")),
+        format!("Documentation: {}", text(&info.documentation, "documentation")),
+        format!("Readme: {}", text(&info.readme, "readme")),
+        format!("Keywords: {}", list(&info.keywords, "keywords")),
+        format!("Categories: {}", list(&info.categories, "categories")),
+        format!("Publish: {}{}", publish, info.source_note("publish")),
+        format!("Default Run: {}", text(&info.default_run, "default-run")),
+        format!("Path: {:?}", info.path),
+    ];
+
+    if !info.targets.is_empty() {
+        lines.push("Targets:".to_string());
+        for target in &info.targets {
+            let declared = if target.declared { " (declared)" } else { "" };
+            lines.push(format!("  {:<8} {:<20} {}{}", target.kind, target.name, target.path.display(), declared));
+        }
+    }
+
+    if !info.dependencies.is_empty() {
+        lines.push("Dependencies:".to_string());
+        lines.extend(deps::lines(&info.dependencies, "  "));
+    }
+
+    let runnable: Vec<&targets::Target> = info.targets.iter().filter(|t| t.kind == targets::TargetKind::Bin).collect();
+    let built = artifacts::find(info);
+    if runnable.is_empty() {
+        lines.push("This project has no binaries to run.".to_string());
+    } else if built.is_empty() {
+        let target_dir = &artifacts::target_dirs(&info.path)[0];
+        lines.push("No binaries have been built yet; `cargo build --release` would write them to:".to_string());
+        lines.extend(
+            runnable
+                .iter()
+                .filter_map(|t| targets::executable_path(target_dir, "release", t))
+                .map(|b| format!("  {}", b.display())),
+        );
+    } else {
+        lines.push("You can run this project from:".to_string());
+        for artifact in &built {
+            let stale = if artifact.stale { ", STALE: sources changed since" } else { "" };
+            lines.push(format!("  {}", artifact.path.display()));
+            lines.push(format!(
+                "    {} {}, {}, built {} ({}){}",
+                artifact.profile,
+                artifact.kind,
+                util::format_size(artifact.size),
+                util::format_time(artifact.modified),
+                util::format_age(artifact.modified),
+                stale
+            ));
+        }
+        let missing: Vec<&str> = runnable
+            .iter()
+            .filter(|t| !built.iter().any(|a| a.kind == t.kind && a.target == t.name))
+            .map(|t| t.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            lines.push(format!("Not built yet: {}", missing.join(", ")));
+        }
+    }
+
+    lines.extend(git_details(info));
+
+    if info.is_workspace() {
+        lines.push(format!("Workspace Members: {}", info.members.len()));
+        for member in &info.members {
+            lines.push(format!("  - {}", member.name));
+            lines.push(format!("    Description: {}{}", member.description.as_deref().unwrap_or("No description"), member.source_note("description")));
+            lines.push(format!("    Version: {}{}", member.version.as_deref().unwrap_or("Unknown"), member.source_note("version")));
+            lines.push(format!("    Edition: {}", member.edition().unwrap_or("None")));
+            lines.push(format!("    Dependencies: {}", member.dependencies.len()));
+            lines.push(format!("    Path: {:?}", member.path));
+        }
+    }
+    lines
+}
+
+/// Builds the Git section of the details view.
+///
+/// It shows the branch and how far it is ahead of and behind its upstream, the number of
+/// changed and untracked files in the project, the stash and the last commit.
+fn git_details(info: &ProjectInfo) -> Vec<String> {
+    let Some(status) = git::status(&info.path) else {
+        return vec!["Git: Not a repository".to_string()];
+    };
+
+    let mut branch = status.branch.clone().unwrap_or_else(|| "detached HEAD".to_string());
+    match &status.upstream {
+        Some(upstream) => branch.push_str(&format!(", tracking {} ({} ahead, {} behind)", upstream, status.ahead, status.behind)),
+        None if status.branch.is_some() => branch.push_str(", no upstream"),
+        None => {}
+    }
+    let changes = if status.is_dirty() {
+        format!("{} changed, {} untracked", status.changed, status.untracked)
+    } else {
+        "None, the working tree is clean".to_string()
+    };
+    let last_commit = match &status.last_commit {
+        Some(commit) => format!("{} ({}) by {}: {}", util::format_time(commit.time), util::format_age(commit.time), commit.author, commit.subject),
+        None => "None".to_string(),
+    };
+
+    vec![
+        "Git:".to_string(),
+        format!("  Branch: {}", branch),
+        format!("  Uncommitted Changes: {}", changes),
+        format!("  Stashes: {}", status.stashes),
+        format!("  Last Commit: {}", last_commit),
+    ]
+}
+
+/// Runs a cargo command in a project's directory, with the terminal's stdin, stdout and stderr.
+///
+/// # Arguments
+///
+/// * `info` - The project to run the command in.
+/// * `command` - The cargo subcommand, one of `cargo::COMMANDS`.
+/// * `args` - Further arguments for cargo.
+///
+/// # Returns
+///
+/// The exit code of cargo, or 1 if it could not be started.
+fn run_cargo(info: &ProjectInfo, command: &str, args: &[String]) -> i32 {
+    let mut command_line = format!("cargo {}", command);
+    for arg in args {
+        command_line.push(' ');
+        command_line.push_str(arg);
+    }
+    eprintln!("Running `{}` in {}", command_line, info.path.display());
+
+    match cargo::cargo(&info.path, command, args) {
+        Ok(status) => cargo::exit_code(status),
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
+
+/// Runs a cargo command in every listed project and prints a summary of the results.
+///
+/// Workspaces count as one project, so the command runs once at the workspace root; pass
+/// `--workspace` to cargo to include every member of a workspace with a root package. When
+/// the filters select only some members of a workspace, the caller passes those members
+/// instead, as `filter::packages` does.
+///
+/// # Arguments
+///
+/// * `selected` - The projects to run the command in, in order.
+/// * `command` - The cargo subcommand, e.g. `test`.
+/// * `args` - Further arguments for cargo.
+/// * `jobs` - How many projects to run at once.
+/// * `log_dir` - The directory for the log files, or `None` for the default.
+///
+/// # Returns
+///
+/// The exit code for the tool: 0 if the command passed everywhere, 1 otherwise.
+fn run_each(selected: &[&ProjectInfo], command: &str, args: &[String], jobs: usize, log_dir: Option<&PathBuf>) -> i32 {
+    if selected.is_empty() {
+        eprintln!("No projects to run `cargo {}` in.", command);
+        return 1;
+    }
+
+    let log_dir = match batch::log_dir(log_dir.map(PathBuf::as_path), command) {
+        Ok(dir) => dir,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 1;
+        }
+    };
+    let duplicates = duplicate_names(selected.iter().copied());
+    let labels: Vec<String> = selected.iter().map(|info| display_name(info, &duplicates)).collect();
+
+    println!(
+        "Running `cargo {}` in {} project{}, {} at a time. Logs go to {}",
+        std::iter::once(command).chain(args.iter().map(String::as_str)).collect::<Vec<_>>().join(" "),
+        selected.len(),
+        if selected.len() == 1 { "" } else { "s" },
+        jobs.min(selected.len()),
+        log_dir.display()
+    );
+    let outcomes = batch::each(selected, &labels, command, args, jobs, &log_dir);
+    batch::print_summary(&labels, &outcomes);
+
+    if outcomes.iter().all(|outcome| outcome.status == batch::Status::Passed) {
+        0
+    } else {
+        1
+    }
+}
+
+/// Removes target directories, single profiles or stale toolchain output, after confirmation.
+///
+/// What is removed is listed first. Nothing is removed with `--dry-run`, and without `--yes`
+/// the user is asked to confirm, which needs a terminal. Whole target directories and single
+/// profiles must be selected with `--older-than` and/or `--larger-than`, so a bare `clean`
+/// never removes everything.
+///
+/// A shared target directory that projects left out by the filters also build into is
+/// skipped, and reported, unless only stale toolchain output is removed, since that is of
+/// no use to any project.
+///
+/// # Arguments
+///
+/// * `listed` - The projects whose target directories may be cleaned.
+/// * `all` - Every project scanned, selected or not.
+/// * `matches` - The arguments of the `clean` subcommand.
+///
+/// # Returns
+///
+/// The exit code for the tool: 0 on success or when nothing was removed on purpose, 1 on errors.
+fn run_clean(listed: &[&ProjectInfo], all: &[&ProjectInfo], matches: &ArgMatches) -> i32 {
+    let criteria = disk::Criteria {
+        older_than: matches.get_one::<std::time::Duration>("older-than").copied(),
+        larger_than: matches.get_one::<u64>("larger-than").copied(),
+    };
+    let mode = if matches.get_flag("toolchains") {
+        disk::CleanMode::Toolchains
+    } else if matches.get_flag("profiles") {
+        disk::CleanMode::Profiles
+    } else {
+        disk::CleanMode::Target
+    };
+    if mode != disk::CleanMode::Toolchains && criteria.older_than.is_none() && criteria.larger_than.is_none() {
+        eprintln!("Choose what to remove with --older-than, --larger-than or --toolchains.");
+        return 1;
+    }
+
+    let duplicates = duplicate_names(listed.iter().copied());
+    let labels: Vec<String> = listed.iter().map(|info| display_name(info, &duplicates)).collect();
+    let mut usages = disk::usage(listed, &labels);
+    if mode != disk::CleanMode::Toolchains {
+        let selected: BTreeSet<&PathBuf> = listed.iter().map(|info| &info.path).collect();
+        let others: Vec<&ProjectInfo> = all.iter().copied().filter(|info| !selected.contains(&info.path)).collect();
+        let duplicates = duplicate_names(all.iter().copied());
+        let other_labels: Vec<String> = others.iter().map(|info| display_name(info, &duplicates)).collect();
+        let shared = disk::unselected_owners(&usages, &others, &other_labels);
+        for (dir, owners) in &shared {
+            println!("Skipping {}: also used by {}, which the filters left out.", dir.display(), owners.join(", "));
+        }
+        usages.retain(|usage| !shared.contains_key(&usage.dir));
+    }
+    let removals = disk::removals(&usages, mode, &criteria);
+    if removals.is_empty() {
+        println!("Nothing to remove.");
+        return 0;
+    }
+
+    let total: u64 = removals.iter().map(|r| r.size).sum();
+    let dry_run = matches.get_flag("dry-run");
+    let confirmed = matches.get_flag("yes");
+    println!("{} {} item{}, {} in total:", if confirmed && !dry_run { "Removing" } else { "Would remove" }, removals.len(), if removals.len() == 1 { "" } else { "s" }, util::format_size(total));
+    disk::print_removals(&removals);
+    if dry_run {
+        return 0;
+    }
+
+    if !confirmed {
+        if !io::stdin().is_terminal() {
+            eprintln!("Not removing anything without confirmation; pass --yes to remove without asking.");
+            return 1;
+        }
+        print!("Remove these {} items? [y/N] ", removals.len());
+        io::stdout().flush().expect("Failed to flush stdout");
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).expect("Failed to read input");
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Nothing removed.");
+            return 0;
+        }
+    }
+
+    let mut freed = 0;
+    let mut failed = false;
+    for removal in &removals {
+        match disk::remove(removal) {
+            Ok(()) => freed += removal.size,
+            Err(message) => {
+                eprintln!("{}", message);
+                failed = true;
+            }
+        }
+    }
+    println!("Freed {}.", util::format_size(freed));
+    i32::from(failed)
+}
+
+/// Prints the changes to the project index as they happen, until interrupted.
+///
+/// Text output has one line per change; with `--format json` every change is a JSON object
+/// on a line of its own, so other tools can follow the output as JSON lines.
+///
+/// # Arguments
+///
+/// * `live` - The watcher, or the message saying why it could not be started.
+/// * `filters` - Only changes to projects that meet every filter are printed.
+/// * `format` - `Format::Text` or `Format::Json`.
+///
+/// # Returns
+///
+/// The exit code: 1 if watching failed or the format is not supported.
+fn run_watch(live: Result<watch::Live, String>, filters: &[filter::Filter], format: Format) -> i32 {
+    if format != Format::Text && format != Format::Json {
+        eprintln!("`watch` writes text or JSON lines; use --format text or --format json");
+        return 1;
+    }
+    let mut live = match live {
+        Ok(live) => live,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 1;
+        }
+    };
+    eprintln!("Watching {} projects for changes; press Ctrl+C to stop.", live.projects().len());
+
+    loop {
+        let events = match live.wait(None) {
+            Ok(events) => events,
+            Err(message) => {
+                eprintln!("{}", message);
+                return 1;
+            }
+        };
+        for event in events.iter().filter(|event| filter::matches_all(&event.info, filters)) {
+            if format == Format::Json {
+                println!("{}", output::to_json(&event.record()));
+            } else {
+                println!("{}", event.text());
+            }
+        }
+    }
+}
+
+/// Builds the command-line interface.
+///
+/// The available arguments are:
+/// - `--help`: Displays the help manual.
+/// - `--list`: Lists all available projects; kept for compatibility with `list`.
+/// - `--max-depth <N>`: Limits how many directory levels below the root are searched.
+/// - `--nested`: Keeps searching inside projects for further nested projects.
+/// - `--include-hidden`: Also searches hidden directories.
+/// - `--jobs <N>`: Sets how many threads search for projects or, for `each`, how many projects run at once.
+/// - `--refresh`: Parses every manifest again instead of reading unchanged ones from the cache.
+/// - `--config <path>`: Reads the configuration from the given file.
+/// - `--root <dir>`: Searches the given directory instead of the configured roots; repeatable.
+/// - `--format <format>`: Writes the output as `text`, `json`, `csv`, `tsv` or `toml`.
+/// - `--columns <list>`: Shows the list as a table of the given comma-separated columns.
+/// - `--git`: Adds the Git status columns to the list.
+/// - `--sort <key>`, `--reverse`: Orders the list by name, mtime, size, version, edition, last-commit or loc.
+/// - `--filter <expr>`, `--has-bin`, `--dirty`, `--stale <age>`: Only lists the projects that match.
+/// - `--interactive`: Shows the selection prompt even when not attached to a terminal.
+/// - `--duplicates`: Reports package names that appear more than once instead of listing projects.
+///
+/// The subcommands are `list`, `show <name|path>`, `deps <name|path>`, `graph`,
+/// `rdeps <name|path>`, `drift`, `outdated [name|path]`, `find <query>`, `config show`, `index stats`, and
+/// `build`, `run`, `test`, `check`, `clippy` and `doc`, which run the matching cargo
+/// command in a project's directory and take further cargo arguments after `--`, and
+/// `each <command> [args]`, which runs a cargo command in every project, `disk` and
+/// `clean`, which report and free the space taken by target directories, and `watch`,
+/// which reports changes to the projects as they happen.
+fn build_cli() -> Command {
+    Command::new("My Rust Manager")
         .version("0.1.0")
         .author("Your Name <you@example.com>")
         .about("A manual and manager of my Rust projects")
+        .disable_help_flag(true)
         .arg(Arg::new("help")
              .short('h')
              .long("help")
-             .action(clap::ArgAction::Help)
+             .global(true)
+             .action(ArgAction::Help)
              .help("Displays the manual page"))
         .arg(Arg::new("list")
              .short('l')
              .long("list")
-             .help("Lists all available projects"))
-        .get_matches();
+             .action(ArgAction::SetTrue)
+             .help("Lists all available projects (same as `list`)"))
+        .arg(Arg::new("config")
+             .long("config")
+             .value_name("PATH")
+             .global(true)
+             .value_parser(clap::value_parser!(PathBuf))
+             .help("Read the configuration from this file instead of ~/.config/my_rust/config.toml"))
+        .arg(Arg::new("root")
+             .long("root")
+             .value_name("DIR")
+             .global(true)
+             .action(ArgAction::Append)
+             .value_parser(clap::value_parser!(PathBuf))
+             .help("Search this directory for projects; can be given more than once"))
+        .arg(Arg::new("max-depth")
+             .long("max-depth")
+             .global(true)
+             .value_name("N")
+             .value_parser(clap::value_parser!(usize))
+             .help("Maximum number of directory levels to search below the root"))
+        .arg(Arg::new("nested")
+             .long("nested")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Keep searching inside projects for nested projects"))
+        .arg(Arg::new("include-hidden")
+             .long("include-hidden")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Also search hidden directories"))
+        .arg(Arg::new("jobs")
+             .short('j')
+             .long("jobs")
+             .value_name("N")
+             .global(true)
+             .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+             .help("How many threads search for projects (default: one per CPU); for `each`, how many projects run at once instead (default: 1)"))
+        .arg(Arg::new("refresh")
+             .long("refresh")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Parse every Cargo.toml again instead of reading unchanged ones from the cache"))
+        .arg(Arg::new("format")
+             .long("format")
+             .value_name("FORMAT")
+             .global(true)
+             .value_parser(clap::builder::PossibleValuesParser::new(Format::NAMES))
+             .help("Output format; anything but `text` skips the selection prompt"))
+        .arg(Arg::new("columns")
+             .long("columns")
+             .value_name("LIST")
+             .global(true)
+             .value_delimiter(',')
+             .value_parser(clap::builder::PossibleValuesParser::new(output::COLUMNS.iter().chain(git::COLUMNS)))
+             .help("Columns to show in the list and in CSV/TSV output, separated by commas"))
+        .arg(Arg::new("git")
+             .long("git")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Add the Git columns: branch, upstream, ahead/behind, changed and untracked files, stashes and last commit"))
+        .arg(Arg::new("sort")
+             .long("sort")
+             .value_name("KEY")
+             .global(true)
+             .value_parser(clap::builder::PossibleValuesParser::new(sort::SortKey::NAMES))
+             .help("Sort order of the list; size, mtime, version, last-commit and loc put the largest or newest first"))
+        .arg(Arg::new("reverse")
+             .long("reverse")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Reverse the sort order"))
+        .arg(Arg::new("filter")
+             .long("filter")
+             .value_name("EXPR")
+             .global(true)
+             .action(ArgAction::Append)
+             .value_parser(filter::Filter::parse)
+             .help("Only list projects matching e.g. 'edition<2021', 'license=MIT' or a fuzzy query; repeatable"))
+        .arg(Arg::new("has-bin")
+             .long("has-bin")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Only list projects with a binary target"))
+        .arg(Arg::new("dirty")
+             .long("dirty")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Only list projects with uncommitted changes or untracked files"))
+        .arg(Arg::new("stale")
+             .long("stale")
+             .value_name("AGE")
+             .global(true)
+             .value_parser(util::parse_duration)
+             .help("Only list projects where no file changed within AGE, e.g. 90d"))
+        .arg(Arg::new("interactive")
+             .short('i')
+             .long("interactive")
+             .global(true)
+             .action(ArgAction::SetTrue)
+             .help("Show the selection prompt even when not running in a terminal"))
+        .arg(Arg::new("duplicates")
+             .long("duplicates")
+             .action(ArgAction::SetTrue)
+             .help("Reports package names that appear more than once, with their paths and versions"))
+        .subcommand(Command::new("list")
+             .about("Lists all available projects"))
+        .subcommand(Command::new("show")
+             .about("Shows the details of a project")
+             .arg(Arg::new("project")
+                  .value_name("NAME|PATH")
+                  .required(true)
+                  .help("The package name or directory of the project")))
+        .subcommand(Command::new("deps")
+             .about("Lists the dependencies of a project with their kind, source, version requirement and features")
+             .arg(Arg::new("project")
+                  .value_name("NAME|PATH")
+                  .required(true)
+                  .help("The package name or directory of the project")))
+        .subcommand(Command::new("graph")
+             .about("Shows which local projects depend on each other through path dependencies, and any cycles")
+             .arg(Arg::new("dot")
+                  .long("dot")
+                  .action(ArgAction::SetTrue)
+                  .conflicts_with("mermaid")
+                  .help("Write the graph in Graphviz DOT"))
+             .arg(Arg::new("mermaid")
+                  .long("mermaid")
+                  .action(ArgAction::SetTrue)
+                  .help("Write the graph as a Mermaid flowchart")))
+        .subcommand(Command::new("rdeps")
+             .about("Lists the local projects that depend on a project, directly or indirectly")
+             .arg(Arg::new("project")
+                  .value_name("NAME|PATH")
+                  .required(true)
+                  .help("The package name or directory of the project")))
+        .subcommand(Command::new("drift")
+             .about("Reports crates that the projects use at different versions, from Cargo.lock or Cargo.toml"))
+        .subcommand(Command::new("outdated")
+             .about("Compares dependencies with the newest versions in Cargo's local registry index, offline")
+             .arg(Arg::new("project")
+                  .value_name("NAME|PATH")
+                  .help("Only check this project instead of every listed one"))
+             .arg(Arg::new("index")
+                  .long("index")
+                  .value_name("DIR")
+                  .value_parser(clap::value_parser!(PathBuf))
+                  .help("Read this directory, laid out like the crates.io index, instead of ~/.cargo/registry/index"))
+             .arg(Arg::new("all")
+                  .long("all")
+                  .action(ArgAction::SetTrue)
+                  .help("Also list dependencies that are up to date")))
+        .subcommand(Command::new("find")
+             .about("Lists projects ranked by fuzzy match on name, keywords, categories, description and path")
+             .arg(Arg::new("query")
+                  .required(true)
+                  .help("The characters to look for, in order; case is ignored")))
+        .subcommands(cargo::COMMANDS.iter().map(|(name, about)| {
+             Command::new(*name)
+                 .about(*about)
+                 .arg(Arg::new("project")
+                      .value_name("NAME|PATH")
+                      .required(true)
+                      .help("The package name or directory of the project"))
+                 .arg(Arg::new("args")
+                      .value_name("ARGS")
+                      .num_args(0..)
+                      .last(true)
+                      .allow_hyphen_values(true)
+                      .help("Arguments passed on to cargo, e.g. `-- --release`"))
+         }))
+        .subcommand(Command::new("each")
+             .about("Runs a cargo command in every project and reports which ones failed")
+             .arg(Arg::new("log-dir")
+                  .long("log-dir")
+                  .value_name("DIR")
+                  .value_parser(clap::value_parser!(PathBuf))
+                  .help("Write the log files here instead of ~/.cache/my_rust/logs/<command>-<time>"))
+             .arg(Arg::new("command")
+                  .value_name("COMMAND")
+                  .required(true)
+                  .help("The cargo subcommand to run, e.g. check, test, fmt or clippy"))
+             .arg(Arg::new("args")
+                  .value_name("ARGS")
+                  .num_args(0..)
+                  .trailing_var_arg(true)
+                  .allow_hyphen_values(true)
+                  .help("Arguments passed on to cargo, e.g. `--check` for fmt")))
+        .subcommand(Command::new("disk")
+             .about("Reports how much space each target directory takes, largest first"))
+        .subcommand(Command::new("clean")
+             .about("Removes target directories, or parts of them, to free disk space")
+             .arg(Arg::new("older-than")
+                  .long("older-than")
+                  .value_name("AGE")
+                  .value_parser(util::parse_duration)
+                  .help("Only remove what was last built longer ago than this, e.g. 30d, 12w or 1y"))
+             .arg(Arg::new("larger-than")
+                  .long("larger-than")
+                  .value_name("SIZE")
+                  .value_parser(util::parse_size)
+                  .help("Only remove what takes up at least this much space, e.g. 500M or 2G"))
+             .arg(Arg::new("profiles")
+                  .long("profiles")
+                  .action(ArgAction::SetTrue)
+                  .conflicts_with("toolchains")
+                  .help("Remove single profiles such as target/debug instead of whole target directories"))
+             .arg(Arg::new("toolchains")
+                  .long("toolchains")
+                  .action(ArgAction::SetTrue)
+                  .help("Only remove build output left behind by older Rust toolchains"))
+             .arg(Arg::new("dry-run")
+                  .short('n')
+                  .long("dry-run")
+                  .action(ArgAction::SetTrue)
+                  .help("Show what would be removed without removing anything"))
+             .arg(Arg::new("yes")
+                  .short('y')
+                  .long("yes")
+                  .action(ArgAction::SetTrue)
+                  .help("Remove without asking for confirmation")))
+        .subcommand(Command::new("watch")
+             .about("Prints a line whenever a project is added, removed, moved or its Cargo.toml changes"))
+        .subcommand(Command::new("index")
+             .about("Inspects the cache of parsed manifests")
+             .subcommand_required(true)
+             .subcommand(Command::new("stats")
+                  .about("Shows where the cache is and how many of its entries are up to date")))
+        .subcommand(Command::new("config")
+             .about("Inspects the configuration")
+             .subcommand_required(true)
+             .subcommand(Command::new("show")
+                  .about("Prints the effective configuration and where each value came from")))
+}
 
-    // Default to listing projects if no arguments are provided
-    if matches.contains_id("list") || !matches.args_present() {
-        // List projects by default, or explicitly if `--list` is passed
-        let home_dir = dirs::home_dir().expect("Could not find home directory");
-        let root_path = home_dir.join("rust");
+/// Reads the options controlling the walk from the configuration and the command line.
+///
+/// For `each`, `--jobs` sets how many projects run at once, so the walk keeps its default
+/// of one thread per CPU.
+fn scan_options(config: &config::Config, matches: &ArgMatches) -> ScanOptions {
+    let jobs = matches.get_one::<usize>("jobs").copied().filter(|_| matches.subcommand_name() != Some("each"));
+    ScanOptions {
+        max_depth: config.max_depth.value,
+        nested: matches.get_flag("nested"),
+        include_hidden: matches.get_flag("include-hidden"),
+        exclude: config.exclude.value.clone(),
+        jobs: jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+    }
+}
 
-        if !root_path.exists() {
-            println!("Sorry, no Rust projects found.");
+/// Searches every configured root and merges the results into a single index.
+///
+/// Roots that do not exist are skipped, and a directory reached through overlapping roots
+/// is only indexed once. Manifests that did not change since the last run are read from
+/// the cache, which the caller saves afterwards.
+///
+/// # Returns
+///
+/// The index, and the canonical path of every directory that was visited.
+fn scan(config: &config::Config, options: &ScanOptions, cache: &cache::Cache) -> (ProjectIndex, Vec<PathBuf>) {
+    let mut projects = ProjectIndex::new();
+    let mut dirs = Vec::new();
+    for root in config.roots.value.iter().filter(|root| root.exists()) {
+        let (found, visited) = find_projects(root, options, cache);
+        for (path, info) in found {
+            projects.entry(path).or_insert(info);
+        }
+        dirs.extend(visited);
+    }
+    (projects, dirs)
+}
+
+/// Main function to handle the execution of the program.
+///
+/// This function sets up the argument parsing, handles Ctrl+C interrupts, and
+/// defaults to listing projects if no subcommand is given. See `build_cli` for the
+/// available arguments.
+///
+/// Scan roots can also be set with the colon-separated `MY_RUST_ROOTS` environment variable,
+/// which takes precedence over the configuration file but not over `--root`.
+///
+/// The selection prompt only runs when stdin and stdout are both terminals, or when
+/// `--interactive` is passed, so the tool never blocks on input in scripts or pipes.
+fn main() {
+    // Handle Ctrl+C to exit the program
+    ctrlc::set_handler(move || {
+        // A running cargo command gets the interrupt too and decides how to exit
+        if cargo::child_running() {
             return;
         }
+        println!("\nProgram interrupted. Exiting...");
+        std::process::exit(0);
+    }).expect("Error setting Ctrl+C handler");
+
+    let matches = build_cli().get_matches();
+
+    let mut config = match config::load(matches.get_one::<PathBuf>("config").map(PathBuf::as_path)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+    config::apply_env(&mut config);
+    if let Some(roots) = matches.get_many::<PathBuf>("root") {
+        config.roots.set(roots.cloned().collect(), config::Source::CommandLine("--root"));
+    }
+    if let Some(&depth) = matches.get_one::<usize>("max-depth") {
+        config.max_depth.set(Some(depth), config::Source::CommandLine("--max-depth"));
+    }
 
-        let projects = find_projects(&root_path);
-        display_projects(&projects);
+    if let Some(sort) = matches.get_one::<String>("sort") {
+        config.sort.set(sort.clone(), config::Source::CommandLine("--sort"));
+    }
+    let sort = match config.sort.value.parse::<sort::SortKey>() {
+        Ok(sort) => sort,
+        Err(message) => {
+            eprintln!("{} (from {})", message, config.sort.source);
+            std::process::exit(1);
+        }
+    };
+    let mut filters: Vec<filter::Filter> = matches.get_many::<filter::Filter>("filter").map(|f| f.cloned().collect()).unwrap_or_default();
+    if matches.get_flag("has-bin") {
+        filters.push(filter::Filter::HasBin);
+    }
+    if matches.get_flag("dirty") {
+        filters.push(filter::Filter::Dirty);
+    }
+    if let Some(&age) = matches.get_one::<std::time::Duration>("stale") {
+        filters.push(filter::Filter::Untouched(age));
+    }
+    let selection = Selection { filters, sort, explicit_sort: matches.get_one::<String>("sort").is_some(), reverse: matches.get_flag("reverse") };
+
+    if let Some(format) = matches.get_one::<String>("format") {
+        config.format.set(format.clone(), config::Source::CommandLine("--format"));
+    }
+    let format = match config.format.value.parse::<Format>() {
+        Ok(format) => format,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    };
+    let interactive = format == Format::Text
+        && (matches.get_flag("interactive") || (io::stdin().is_terminal() && io::stdout().is_terminal()));
+
+    if let Some(("config", _)) = matches.subcommand() {
+        config::show(&config);
+        return;
+    }
+    if let Some(("index", _)) = matches.subcommand() {
+        let stats = cache::Cache::load(false).stats();
+        if format == Format::Text {
+            cache::print_stats(&stats);
+        } else {
+            let columns = ["path", "size", "written", "manifests", "projects", "up-to-date", "outdated", "missing"];
+            output::print_records(vec![cache::stats_record(&stats)], "index", &columns, format);
+        }
+        return;
+    }
+
+    if format == Format::Text && !config.roots.value.iter().any(|root| root.exists()) {
+        println!("Sorry, no Rust projects found.");
+        return;
+    }
+    // Unchanged manifests are read from the index cache unless `--refresh` is given
+    let options = scan_options(&config, &matches);
+    let mut cache = cache::Cache::load(matches.get_flag("refresh"));
+    let (projects, scanned_dirs) = scan(&config, &options, &cache);
+    if let Err(message) = cache.save() {
+        eprintln!("{}", message);
+    }
+    // `watch` and the full-screen browser keep following the filesystem after the scan
+    let roots: Vec<PathBuf> = config.roots.value.iter().filter(|root| root.exists()).cloned().collect();
+    let follow = || watch::Live::start(&roots, options, cache, projects.clone(), &scanned_dirs);
+    let mut columns: Vec<String> = matches.get_many::<String>("columns").map(|c| c.cloned().collect()).unwrap_or_default();
+    if matches.get_flag("git") {
+        if columns.is_empty() {
+            let defaults: &[&str] = if format == Format::Text { &["name"] } else { output::COLUMNS };
+            columns = defaults.iter().map(|c| c.to_string()).collect();
+        }
+        for column in git::COLUMNS {
+            if !columns.iter().any(|c| c == column) {
+                columns.push(column.to_string());
+            }
+        }
+    }
+
+    match matches.subcommand() {
+        Some(("show", sub)) => {
+            let query = sub.get_one::<String>("project").expect("project is required");
+            match resolve_project(&projects, query) {
+                Ok(info) if format == Format::Text => display_project_details(info),
+                Ok(info) => output::print_project(info, format),
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("deps", sub)) => {
+            let query = sub.get_one::<String>("project").expect("project is required");
+            match resolve_project(&projects, query) {
+                Ok(info) if format == Format::Text => display_dependencies(info),
+                Ok(info) => {
+                    let columns = ["project", "name", "package", "kind", "target", "source", "req", "optional", "default-features", "features", "inherited"];
+                    output::print_records(dependency_records(info), "dependency", &columns, format);
+                }
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("graph", sub)) => {
+            let listed = selected_projects(&projects, &selection);
+            let duplicates = duplicate_names(with_members(listed.iter().copied()));
+            let graph = graph::Graph::build(&listed, |info| display_name(info, &duplicates));
+            if sub.get_flag("dot") {
+                print!("{}", graph.to_dot());
+            } else if sub.get_flag("mermaid") {
+                print!("{}", graph.to_mermaid());
+            } else {
+                match format {
+                    Format::Text => display_graph(&graph),
+                    Format::Json => println!("{}", output::to_json(&graph.record())),
+                    Format::Toml => print!("{}", toml::to_string(&graph.record()).expect("Failed to serialise TOML")),
+                    Format::Csv | Format::Tsv => {
+                        let columns = ["from", "to", "kinds", "from-path", "to-path"];
+                        output::print_records(graph.edge_records(), "dependency", &columns, format);
+                    }
+                }
+            }
+        }
+        Some(("drift", _)) => {
+            let listed = selected_projects(&projects, &selection);
+            let duplicates = duplicate_names(listed.iter().copied());
+            let labels: Vec<String> = listed.iter().map(|info| display_name(info, &duplicates)).collect();
+            let drifts = drift::find(&listed, &labels);
+            if format == Format::Text {
+                drift::print_drift(&drifts, listed.len());
+            } else {
+                let columns = ["rank", "crate", "range", "version", "locked", "incompatible", "crate-projects", "count", "projects"];
+                output::print_records(drift::records(&drifts), "drift", &columns, format);
+            }
+        }
+        Some(("outdated", sub)) => {
+            let listed = match sub.get_one::<String>("project") {
+                Some(query) => match resolve_project(&projects, query) {
+                    Ok(info) => vec![info],
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        std::process::exit(1);
+                    }
+                },
+                None => selected_projects(&projects, &selection),
+            };
+            let index = match registry::Index::open(sub.get_one::<PathBuf>("index").map(PathBuf::as_path)) {
+                Ok(index) => index,
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                }
+            };
+            let duplicates = duplicate_names(listed.iter().copied());
+            let labels: Vec<String> = listed.iter().map(|info| display_name(info, &duplicates)).collect();
+            let checks = outdated::check(&listed, &labels, &index);
+            let shown: Vec<&outdated::Check> = checks.iter().filter(|c| sub.get_flag("all") || c.needs_attention()).collect();
+            if format == Format::Text {
+                outdated::print_checks(&shown, &checks, &index);
+            } else {
+                let columns = ["project", "name", "package", "kind", "req", "locked", "compatible", "latest", "yanked", "found", "compatible-upgrade", "incompatible-upgrade"];
+                output::print_records(outdated::records(&shown), "dependency", &columns, format);
+            }
+        }
+        Some(("rdeps", sub)) => {
+            let query = sub.get_one::<String>("project").expect("project is required");
+            let info = match resolve_project(&projects, query) {
+                Ok(info) => info,
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                }
+            };
+            let everything: Vec<&ProjectInfo> = projects.values().collect();
+            let duplicates = duplicate_names(all_projects(&projects));
+            let graph = graph::Graph::build(&everything, |info| display_name(info, &duplicates));
+            // Asking about a workspace means asking about all of its packages
+            let targets: Vec<usize> = (0..graph.nodes.len())
+                .filter(|&node| std::iter::once(info).chain(&info.members).any(|package| package.path == graph.nodes[node].path))
+                .collect();
+            let mut dependents = graph.dependents(&targets);
+            dependents.retain(|dependent| filter::matches_all(graph.nodes[dependent.node], &selection.filters));
+            if format == Format::Text {
+                display_dependents(&graph, &display_name(info, &duplicates), &dependents);
+            } else {
+                let records = dependents
+                    .iter()
+                    .map(|dependent| {
+                        let mut record = toml::value::Table::new();
+                        record.insert("name".into(), Value::String(graph.labels[dependent.node].clone()));
+                        record.insert("path".into(), Value::String(graph.nodes[dependent.node].path.display().to_string()));
+                        record.insert("depth".into(), Value::Integer(dependent.depth as i64));
+                        let edge = &graph.edges[dependent.edge];
+                        record.insert("via".into(), Value::String(graph.labels[edge.to].clone()));
+                        record.insert("kinds".into(), Value::Array(edge.kinds.iter().map(|kind| Value::String(kind.to_string())).collect()));
+                        Value::Table(record)
+                    })
+                    .collect();
+                output::print_records(records, "dependent", &["name", "path", "depth", "via", "kinds"], format);
+            }
+        }
+        Some((command, sub)) if cargo::is_command(command) => {
+            let query = sub.get_one::<String>("project").expect("project is required");
+            let args: Vec<String> = sub.get_many::<String>("args").map(|a| a.cloned().collect()).unwrap_or_default();
+            match resolve_project(&projects, query) {
+                Ok(info) => std::process::exit(run_cargo(info, command, &args)),
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("each", sub)) => {
+            let command = sub.get_one::<String>("command").expect("command is required");
+            let args: Vec<String> = sub.get_many::<String>("args").map(|a| a.cloned().collect()).unwrap_or_default();
+            let jobs = sub.get_one::<usize>("jobs").copied().unwrap_or(1);
+            let selected = filter::packages(selected_projects(&projects, &selection), &selection.filters);
+            std::process::exit(run_each(&selected, command, &args, jobs, sub.get_one::<PathBuf>("log-dir")));
+        }
+        Some(("disk", _)) => {
+            let listed = selected_projects(&projects, &selection);
+            let duplicates = duplicate_names(listed.iter().copied());
+            let labels: Vec<String> = listed.iter().map(|info| display_name(info, &duplicates)).collect();
+            let usages = disk::usage(&listed, &labels);
+            if format == Format::Text {
+                disk::print_usage(&usages);
+            } else {
+                output::print_records(disk::usage_records(&usages), "target", &["path", "size", "last-build", "projects"], format);
+            }
+        }
+        Some(("clean", sub)) => {
+            let selected = selected_projects(&projects, &selection);
+            let (whole, partial): (Vec<&ProjectInfo>, Vec<&ProjectInfo>) = selected.iter().partition(|info| filter::selects_whole(info, &selection.filters));
+            for info in partial {
+                println!("Skipping {}: the filters select only some of its members, which share one target directory.", info.name);
+            }
+            std::process::exit(run_clean(&whole, &listed_projects(&projects), sub))
+        }
+        Some(("watch", _)) => std::process::exit(run_watch(follow(), &selection.filters, format)),
+        Some(("find", sub)) => {
+            let query = sub.get_one::<String>("query").expect("query is required");
+            let hits = find_matches(&projects, query, &selection);
+            if format == Format::Text {
+                let select = |index: &ProjectIndex| find_matches(index, query, &selection).iter().map(|hit| hit.info.clone()).collect();
+                display_matches(&hits, query, interactive, || follow().ok().map(|live| watch::LiveList::new(live, select)));
+            } else {
+                let found: Vec<&ProjectInfo> = hits.iter().map(|hit| hit.info).collect();
+                output::print_projects(&found, format, &columns);
+            }
+        }
+        // Listing is the default action, so it runs with or without `list` or `--list`
+        _ => {
+            if matches.get_flag("duplicates") {
+                display_duplicates(&projects, format);
+            } else if format == Format::Text {
+                let select = |index: &ProjectIndex| selected_projects(index, &selection).into_iter().cloned().collect();
+                display_projects(&selected_projects(&projects, &selection), &columns, interactive, || follow().ok().map(|live| watch::LiveList::new(live, select)));
+            } else {
+                output::print_projects(&selected_projects(&projects, &selection), format, &columns);
+            }
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    fn write_package(dir: &Path, name: &str) {
+        write_file(&dir.join("Cargo.toml"), &format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name));
+    }
+
+    fn found(root: &Path, options: &ScanOptions) -> Vec<String> {
+        let (projects, _) = find_projects(root, options, &cache::Cache::default());
+        projects.values().map(|info| info.name.clone()).collect()
+    }
+
+    #[test]
+    fn walk_results_do_not_depend_on_the_number_of_threads() {
+        let root = scratch_dir("walk-threads");
+        for group in 0..8 {
+            for project in 0..8 {
+                write_package(&root.join(format!("group{}/p{}", group, project)), &format!("p{}-{}", group, project));
+            }
+        }
+        write_package(&root.join("group3/p3/nested"), "nested");
+        for nested in [false, true] {
+            let single = found(&root, &ScanOptions { jobs: 1, nested, ..ScanOptions::default() });
+            assert_eq!(single.len(), if nested { 65 } else { 64 });
+            for _ in 0..5 {
+                assert_eq!(found(&root, &ScanOptions { jobs: 8, nested, ..ScanOptions::default() }), single);
+            }
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn max_depth_uses_the_shortest_route_through_symlinks() {
+        let root = scratch_dir("walk-symlink-depth");
+        write_package(&root.join("a/b/c/deep"), "deep");
+        std::os::unix::fs::symlink(root.join("a/b/c"), root.join("shortcut")).expect("create symlink");
+        // A link back up the tree must not loop
+        std::os::unix::fs::symlink(&root, root.join("a/up")).expect("create symlink");
+        for jobs in [1, 4] {
+            let options = ScanOptions { jobs, max_depth: Some(3), ..ScanOptions::default() };
+            for _ in 0..5 {
+                assert_eq!(found(&root, &options), ["deep"]);
+            }
+            let options = ScanOptions { jobs, max_depth: Some(1), ..ScanOptions::default() };
+            assert!(found(&root, &options).is_empty());
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn skipped_directories() {
+        let root = Path::new("/r");
+        let options = ScanOptions { exclude: vec!["vendor".into(), "work/old-*".into()], ..ScanOptions::default() };
+        assert!(is_skipped_dir(Path::new("/r/target"), root, &options));
+        assert!(is_skipped_dir(Path::new("/r/x/.git"), root, &options));
+        assert!(is_skipped_dir(Path::new("/r/.hidden"), root, &options));
+        assert!(!is_skipped_dir(Path::new("/r/.hidden"), root, &ScanOptions { include_hidden: true, ..ScanOptions::default() }));
+        assert!(is_skipped_dir(Path::new("/r/a/vendor"), root, &options));
+        assert!(is_skipped_dir(Path::new("/r/work/old-api"), root, &options));
+        assert!(!is_skipped_dir(Path::new("/r/other/work/old-api"), root, &options));
+        assert!(!is_skipped_dir(Path::new("/r/work/new-api"), root, &options));
+    }
+
+    #[test]
+    fn workspace_members_inherit_from_the_workspace() {
+        let root = scratch_dir("parse-workspace");
+        write_file(
+            &root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"2.1.0\"\nedition = \"2021\"\nlicense = \"MIT\"\n",
+        );
+        write_file(
+            &root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\nversion.workspace = true\nedition = { workspace = true }\nlicense = \"Apache-2.0\"\n",
+        );
+        write_file(&root.join("crates/core/src/lib.rs"), "\n");
+        write_package(&root.join("crates/cli"), "cli");
+
+        let info = parse_cargo_toml(&root.join("Cargo.toml")).expect("workspace manifest parses");
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(info.kind, ProjectKind::VirtualWorkspace);
+        assert_eq!(info.members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["cli", "core"]);
+        let core = &info.members[1];
+        assert_eq!((core.version.as_deref(), core.edition.as_deref()), (Some("2.1.0"), Some("2021")));
+        assert_eq!(core.license.as_deref(), Some("Apache-2.0"));
+        assert_eq!(core.inherited, ["version", "edition"]);
+        assert!(info.members[0].inherited.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use semver::{Version, VersionReq};
+use toml::value::Table;
+use toml::Value;
+
+use crate::deps::{DependencyKind, Source};
+use crate::registry::{Index, Release};
+use crate::{drift, ProjectInfo};
+
+/// A registry dependency of a project compared with the versions in the index.
+pub struct Check {
+    /// The name of the project declaring the dependency.
+    pub project: String,
+    /// The dependency's name in the project.
+    pub name: String,
+    /// The crate name, which differs from `name` for a renamed dependency.
+    pub package: String,
+    /// Which table the dependency is declared in.
+    pub kind: DependencyKind,
+    /// The version requirement.
+    pub req: String,
+    /// The newest version in the project's `Cargo.lock` that meets the requirement.
+    pub locked: Option<Version>,
+    /// Whether the locked version has been yanked.
+    pub yanked: bool,
+    /// The newest release in the index that meets the requirement.
+    pub compatible: Option<Version>,
+    /// The newest release in the index.
+    pub latest: Option<Version>,
+    /// Whether the index has the crate at all.
+    pub found: bool,
+}
+
+impl Check {
+    /// Returns `true` if `cargo update` would move the lockfile to a newer version.
+    pub fn has_compatible_upgrade(&self) -> bool {
+        matches!((&self.locked, &self.compatible), (Some(locked), Some(compatible)) if compatible > locked)
+    }
+
+    /// Returns `true` if a newer release exists that the requirement does not allow.
+    pub fn has_incompatible_upgrade(&self) -> bool {
+        match (&self.latest, &self.compatible) {
+            (Some(latest), Some(compatible)) => latest > compatible,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if either kind of upgrade is available.
+    pub fn is_outdated(&self) -> bool {
+        self.has_compatible_upgrade() || self.has_incompatible_upgrade()
+    }
+
+    /// Returns `true` if the dependency is listed without `--all`: it is outdated, or its
+    /// locked version was yanked.
+    pub fn needs_attention(&self) -> bool {
+        self.is_outdated() || self.yanked
+    }
+}
+
+/// Compares the crates.io dependencies of projects with the newest versions in the index.
+///
+/// Every dependency from crates.io of each project and its workspace members is checked
+/// once per requirement; dependencies on other registries, Git or paths are left out. The
+/// locked version comes from the project's `Cargo.lock`, as found by `drift::lockfile`.
+/// Yanked and pre-release versions are never suggested.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to check.
+/// * `labels` - The name each project is shown under, in the same order.
+/// * `index` - The registry index to compare with.
+pub fn check(projects: &[&ProjectInfo], labels: &[String], index: &Index) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for (info, label) in projects.iter().zip(labels) {
+        let locked = drift::lockfile(&info.path).and_then(|lock| drift::locked_versions(&lock)).unwrap_or_default();
+        let mut seen: Vec<(String, String)> = Vec::new();
+        let dependencies = std::iter::once(*info).chain(&info.members).flat_map(|package| &package.dependencies);
+        for dependency in dependencies.filter(|d| d.source == Source::Registry(None)) {
+            let package = dependency.package.clone().unwrap_or_else(|| dependency.name.clone());
+            let req_text = dependency.req.clone().unwrap_or_else(|| "*".to_string());
+            if seen.contains(&(package.clone(), req_text.clone())) {
+                continue;
+            }
+            seen.push((package.clone(), req_text.clone()));
+            let Ok(req) = VersionReq::parse(&req_text) else {
+                continue;
+            };
+
+            let locked = locked
+                .iter()
+                .filter(|(name, _)| *name == package)
+                .filter_map(|(_, version)| Version::parse(version).ok())
+                .filter(|version| req.matches(version))
+                .max();
+            let releases = index.releases(&package);
+            let candidates: Vec<&Release> = releases
+                .iter()
+                .flatten()
+                .filter(|release| !release.yanked && release.version.pre.is_empty())
+                .collect();
+            let yanked = locked.as_ref().is_some_and(|locked| {
+                releases.iter().flatten().any(|release| release.yanked && release.version == *locked)
+            });
+            checks.push(Check {
+                project: label.clone(),
+                name: dependency.name.clone(),
+                kind: dependency.kind,
+                req: req_text,
+                compatible: candidates.iter().filter(|r| req.matches(&r.version)).map(|r| r.version.clone()).max(),
+                latest: candidates.iter().map(|r| r.version.clone()).max(),
+                found: releases.is_some(),
+                package,
+                locked,
+                yanked,
+            });
+        }
+    }
+    checks
+}
+
+/// Prints the outdated dependencies as a table.
+///
+/// `Compatible` is the newest release the requirement allows, which `cargo update` moves
+/// to, and `Latest` the newest release of all, which may need a new requirement.
+///
+/// # Arguments
+///
+/// * `checks` - The dependencies to show.
+/// * `all` - Every dependency checked, which the summary counts.
+/// * `index` - The index the versions came from, named in the summary.
+pub fn print_checks(checks: &[&Check], all: &[Check], index: &Index) {
+    let text = |version: &Option<Version>| version.as_ref().map_or("-".to_string(), Version::to_string);
+    let mut rows: Vec<[String; 6]> = vec![[
+        "PROJECT".to_string(),
+        "DEPENDENCY".to_string(),
+        "REQUIREMENT".to_string(),
+        "LOCKED".to_string(),
+        "COMPATIBLE".to_string(),
+        "LATEST".to_string(),
+    ]];
+    for check in checks {
+        let name = if check.name == check.package { check.name.clone() } else { format!("{} ({})", check.name, check.package) };
+        let locked = if check.yanked { format!("{} (yanked)", text(&check.locked)) } else { text(&check.locked) };
+        let (compatible, latest) = if check.found {
+            (text(&check.compatible), text(&check.latest))
+        } else {
+            ("not in index".to_string(), String::new())
+        };
+        rows.push([check.project.clone(), name, check.req.clone(), locked, compatible, latest]);
+    }
+    if rows.len() > 1 {
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in &rows {
+            let cells: Vec<String> = row.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell)).collect();
+            println!("{}", cells.join("  ").trim_end());
+        }
+        println!();
+    }
+
+    let outdated = all.iter().filter(|c| c.is_outdated()).count();
+    let compatible = all.iter().filter(|c| c.has_compatible_upgrade()).count();
+    let incompatible = all.iter().filter(|c| c.has_incompatible_upgrade()).count();
+    let missing = all.iter().filter(|c| !c.found).count();
+    println!(
+        "{} of {} dependencies have newer versions: {} within their requirement (cargo update), {} beyond it.",
+        outdated,
+        all.len(),
+        compatible,
+        incompatible
+    );
+    let yanked = all.iter().filter(|c| c.yanked).count();
+    if yanked > 0 {
+        println!("{} locked version{} yanked.", yanked, if yanked == 1 { " was" } else { "s were" });
+    }
+    if missing > 0 {
+        let (crates, verb) = if missing == 1 { ("crate", "is") } else { ("crates", "are") };
+        println!("{} {} {} not in the local index; Cargo only keeps the crates it has fetched.", missing, crates, verb);
+    }
+    let dirs: Vec<String> = index.dirs.iter().map(|dir| dir.display().to_string()).collect();
+    println!("Index: {}", dirs.join(", "));
+}
+
+/// Converts checked dependencies into records for machine-readable output.
+pub fn records(checks: &[&Check]) -> Vec<Value> {
+    checks
+        .iter()
+        .map(|check| {
+            let mut record = Table::new();
+            record.insert("project".into(), Value::String(check.project.clone()));
+            record.insert("name".into(), Value::String(check.name.clone()));
+            record.insert("package".into(), Value::String(check.package.clone()));
+            record.insert("kind".into(), Value::String(check.kind.to_string()));
+            record.insert("req".into(), Value::String(check.req.clone()));
+            let versions = [("locked", &check.locked), ("compatible", &check.compatible), ("latest", &check.latest)];
+            for (key, version) in versions {
+                if let Some(version) = version {
+                    record.insert(key.into(), Value::String(version.to_string()));
+                }
+            }
+            record.insert("yanked".into(), Value::Boolean(check.yanked));
+            record.insert("found".into(), Value::Boolean(check.found));
+            record.insert("compatible-upgrade".into(), Value::Boolean(check.has_compatible_upgrade()));
+            record.insert("incompatible-upgrade".into(), Value::Boolean(check.has_incompatible_upgrade()));
+            Value::Table(record)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn write(path: &Path, text: &str) {
+        fs::create_dir_all(path.parent().expect("file has a parent")).expect("create fixture directory");
+        fs::write(path, text).expect("write fixture file");
+    }
+
+    fn entry(version: &str, yanked: bool) -> String {
+        format!(r#"{{"name":"x","vers":"{}","deps":[],"cksum":"00","features":{{}},"yanked":{}}}"#, version, yanked)
+    }
+
+    #[test]
+    fn checks_against_a_fixture_index() {
+        let dir = std::env::temp_dir().join(format!("my_rust-outdated-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let project = dir.join("app");
+        write(
+            &project.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\nrand = \"0.8\"\nnot-published = \"1\"\nlocal = { path = \"../local\" }\n",
+        );
+        write(
+            &project.join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"rand\"\nversion = \"0.8.5\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.130\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        let index_dir = dir.join("index");
+        write(&index_dir.join("se/rd/serde"), &[entry("1.0.130", false), entry("1.0.215", false), entry("2.0.0-alpha.1", false)].join("\n"));
+        write(&index_dir.join("ra/nd/rand"), &[entry("0.8.4", false), entry("0.8.5", true), entry("0.9.0", false)].join("\n"));
+
+        let info = crate::parse_cargo_toml(&project.join("Cargo.toml")).expect("fixture manifest parses");
+        let index = Index::open(Some(&index_dir)).expect("fixture index opens");
+        let checks = check(&[&info], &["app".to_string()], &index);
+        let _ = fs::remove_dir_all(&dir);
+
+        let find = |name: &str| checks.iter().find(|c| c.name == name).unwrap_or_else(|| panic!("{} was not checked", name));
+        let version = |text: &str| Version::parse(text).ok();
+        assert_eq!(checks.len(), 3, "the path dependency is not checked");
+
+        let serde = find("serde");
+        assert!(serde.found);
+        assert_eq!(serde.locked, version("1.0.130"));
+        assert_eq!(serde.compatible, version("1.0.215"));
+        assert_eq!(serde.latest, version("1.0.215"), "pre-releases are never suggested");
+        assert!(serde.has_compatible_upgrade() && !serde.has_incompatible_upgrade() && !serde.yanked);
+
+        let rand = find("rand");
+        assert!(rand.yanked);
+        assert_eq!(rand.locked, version("0.8.5"));
+        assert_eq!(rand.compatible, version("0.8.4"), "yanked releases are never suggested");
+        assert_eq!(rand.latest, version("0.9.0"));
+        assert!(!rand.has_compatible_upgrade() && rand.has_incompatible_upgrade() && rand.needs_attention());
+
+        let missing = find("not-published");
+        assert!(!missing.found);
+        assert_eq!((&missing.locked, &missing.compatible, &missing.latest), (&None, &None, &None));
+        assert!(!missing.needs_attention());
+    }
+}
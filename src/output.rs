@@ -0,0 +1,490 @@
+use std::str::FromStr;
+use toml::value::Table;
+use toml::Value;
+
+use crate::targets::TargetKind;
+use crate::{artifacts, deps, git, util};
+use crate::{ProjectInfo, ProjectKind};
+
+/// The columns that can be shown with `--columns`, in the default CSV and TSV order.
+///
+/// Workspace members get their own row, with the workspace root's path in `workspace`.
+pub const COLUMNS: &[&str] = &[
+    "name",
+    "version",
+    "kind",
+    "path",
+    "root",
+    "workspace",
+    "description",
+    "authors",
+    "license",
+    "license-file",
+    "edition",
+    "rust-version",
+    "repository",
+    "homepage",
+    "documentation",
+    "readme",
+    "keywords",
+    "categories",
+    "publish",
+    "default-run",
+    "bins",
+];
+
+/// The output formats the project list can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The numbered, human-readable list with the interactive selection prompt.
+    Text,
+    /// A JSON array of project objects.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Tab-separated values with a header row.
+    Tsv,
+    /// A TOML document with one `[[project]]` table per project.
+    Toml,
+}
+
+impl Format {
+    /// The names accepted by `--format` and the `format` configuration key.
+    pub const NAMES: &'static [&'static str] = &["text", "json", "csv", "tsv", "toml"];
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!("Unknown output format `{}`; expected one of: {}", s, Format::NAMES.join(", "))),
+        }
+    }
+}
+
+/// Builds the machine-readable record of a project.
+///
+/// Optional fields that are not set are left out of the record rather than written as empty
+/// values, since TOML has no null.
+///
+/// # Arguments
+///
+/// * `info` - The project to describe.
+/// * `with_git` - Whether to add a `git` table with the status of the project's repository.
+///
+/// # Returns
+///
+/// A TOML table with the project's fields and, for workspaces, a `members` array.
+pub fn project_record(info: &ProjectInfo, with_git: bool) -> Value {
+    let mut record = Table::new();
+    record.insert("name".into(), Value::String(info.name.clone()));
+    if let Some(version) = &info.version {
+        record.insert("version".into(), Value::String(version.clone()));
+    }
+    if let Some(description) = &info.description {
+        record.insert("description".into(), Value::String(description.clone()));
+    }
+    record.insert("kind".into(), Value::String(kind_name(info.kind).into()));
+    record.insert("path".into(), Value::String(info.path.display().to_string()));
+    if !info.root.as_os_str().is_empty() {
+        record.insert("root".into(), Value::String(info.root.display().to_string()));
+    }
+    record.insert("authors".into(), string_array(&info.authors));
+    let optional = [
+        ("license", &info.license),
+        ("license-file", &info.license_file),
+        ("rust-version", &info.rust_version),
+        ("repository", &info.repository),
+        ("homepage", &info.homepage),
+        ("documentation", &info.documentation),
+        ("readme", &info.readme),
+        ("default-run", &info.default_run),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            record.insert(key.into(), Value::String(value.clone()));
+        }
+    }
+    if let Some(edition) = info.edition() {
+        record.insert("edition".into(), Value::String(edition.to_string()));
+    }
+    record.insert("keywords".into(), string_array(&info.keywords));
+    record.insert("categories".into(), string_array(&info.categories));
+    let publish = match &info.publish {
+        None => Value::Boolean(true),
+        Some(registries) if registries.is_empty() => Value::Boolean(false),
+        Some(registries) => string_array(registries),
+    };
+    record.insert("publish".into(), publish);
+    let targets = info
+        .targets
+        .iter()
+        .map(|target| {
+            let mut table = Table::new();
+            table.insert("kind".into(), Value::String(target.kind.to_string()));
+            table.insert("name".into(), Value::String(target.name.clone()));
+            table.insert("path".into(), Value::String(target.path.display().to_string()));
+            table.insert("declared".into(), Value::Boolean(target.declared));
+            Value::Table(table)
+        })
+        .collect();
+    record.insert("targets".into(), Value::Array(targets));
+    let artifacts = artifacts::find(info)
+        .into_iter()
+        .map(|artifact| {
+            let mut table = Table::new();
+            table.insert("target".into(), Value::String(artifact.target));
+            table.insert("kind".into(), Value::String(artifact.kind.to_string()));
+            table.insert("profile".into(), Value::String(artifact.profile.into()));
+            table.insert("path".into(), Value::String(artifact.path.display().to_string()));
+            table.insert("size".into(), Value::Integer(artifact.size as i64));
+            table.insert("modified".into(), time_value(artifact.modified));
+            table.insert("stale".into(), Value::Boolean(artifact.stale));
+            Value::Table(table)
+        })
+        .collect();
+    record.insert("artifacts".into(), Value::Array(artifacts));
+    record.insert("dependencies".into(), Value::Array(info.dependencies.iter().map(deps::record).collect()));
+    record.insert("inherited".into(), string_array(&info.inherited));
+    if with_git {
+        if let Some(status) = git::status(&info.path) {
+            record.insert("git".into(), git_record(&status));
+        }
+    }
+    if info.is_workspace() {
+        record.insert("members".into(), Value::Array(info.members.iter().map(|m| project_record(m, with_git)).collect()));
+    }
+    Value::Table(record)
+}
+
+/// Builds the `git` table of a project record.
+fn git_record(status: &git::GitStatus) -> Value {
+    let mut table = Table::new();
+    if let Some(branch) = &status.branch {
+        table.insert("branch".into(), Value::String(branch.clone()));
+    }
+    if let Some(upstream) = &status.upstream {
+        table.insert("upstream".into(), Value::String(upstream.clone()));
+    }
+    table.insert("ahead".into(), Value::Integer(i64::from(status.ahead)));
+    table.insert("behind".into(), Value::Integer(i64::from(status.behind)));
+    table.insert("changed".into(), Value::Integer(status.changed as i64));
+    table.insert("untracked".into(), Value::Integer(status.untracked as i64));
+    table.insert("stashes".into(), Value::Integer(status.stashes as i64));
+    if let Some(commit) = &status.last_commit {
+        let mut last = Table::new();
+        last.insert("date".into(), time_value(commit.time));
+        last.insert("author".into(), Value::String(commit.author.clone()));
+        last.insert("subject".into(), Value::String(commit.subject.clone()));
+        table.insert("last-commit".into(), Value::Table(last));
+    }
+    Value::Table(table)
+}
+
+/// Converts a list of strings into a TOML array.
+fn string_array(items: &[String]) -> Value {
+    Value::Array(items.iter().cloned().map(Value::String).collect())
+}
+
+/// Returns the name of a project kind as written in the `kind` field.
+pub fn kind_name(kind: ProjectKind) -> &'static str {
+    match kind {
+        ProjectKind::Package => "package",
+        ProjectKind::Workspace => "workspace",
+        ProjectKind::VirtualWorkspace => "virtual-workspace",
+    }
+}
+
+/// Writes a list of projects in a machine-readable format.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to write, in listing order.
+/// * `format` - The output format; `Format::Text` is handled by the interactive list instead.
+/// * `columns` - The CSV and TSV columns to write, or all of `COLUMNS` if empty. JSON and
+///   TOML records get a `git` table when any of the `git::COLUMNS` is among them.
+pub fn print_projects(projects: &[&ProjectInfo], format: Format, columns: &[String]) {
+    let with_git = columns.iter().any(|c| git::COLUMNS.contains(&c.as_str()));
+    let records: Vec<Value> = projects.iter().map(|info| project_record(info, with_git)).collect();
+    match format {
+        Format::Json => println!("{}", to_json(&Value::Array(records))),
+        Format::Toml => {
+            let mut document = Table::new();
+            document.insert("project".into(), Value::Array(records));
+            print!("{}", toml::to_string(&Value::Table(document)).expect("Failed to serialise TOML"));
+        }
+        Format::Csv => print!("{}", to_delimited(projects, columns, ',')),
+        Format::Tsv => print!("{}", to_delimited(projects, columns, '\t')),
+        Format::Text => {}
+    }
+}
+
+/// Writes a list of records other than projects in a machine-readable format.
+///
+/// JSON gets an array of the records and TOML one `[[<table>]]` table per record. CSV and
+/// TSV get a header of the given columns and one row per record, with arrays joined by `, `
+/// and nested tables left empty.
+///
+/// # Arguments
+///
+/// * `records` - The records to write; each one is a TOML table.
+/// * `table` - The name of the TOML array of tables, e.g. `target`.
+/// * `columns` - The record fields that make up the CSV and TSV columns.
+/// * `format` - The output format; `Format::Text` writes nothing.
+pub fn print_records(records: Vec<Value>, table: &str, columns: &[&str], format: Format) {
+    match format {
+        Format::Json => println!("{}", to_json(&Value::Array(records))),
+        Format::Toml => {
+            let mut document = Table::new();
+            document.insert(table.into(), Value::Array(records));
+            print!("{}", toml::to_string(&Value::Table(document)).expect("Failed to serialise TOML"));
+        }
+        Format::Csv | Format::Tsv => {
+            let separator = if format == Format::Csv { ',' } else { '\t' };
+            let mut out = String::new();
+            write_row(&mut out, columns.iter().map(|c| c.to_string()).collect(), separator);
+            for record in &records {
+                let cells = columns.iter().map(|c| record.get(*c).map(cell_text).unwrap_or_default()).collect();
+                write_row(&mut out, cells, separator);
+            }
+            print!("{}", out);
+        }
+        Format::Text => {}
+    }
+}
+
+/// Returns the CSV or TSV cell text of a record field.
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(d) => d.to_string(),
+        Value::Array(items) => items.iter().map(cell_text).collect::<Vec<_>>().join(", "),
+        Value::Table(_) => String::new(),
+    }
+}
+
+/// Converts a point in time into a TOML date-time, written as a string in JSON.
+pub fn time_value(time: std::time::SystemTime) -> Value {
+    let timestamp = util::format_timestamp(time);
+    timestamp.parse().map(Value::Datetime).unwrap_or(Value::String(timestamp))
+}
+
+/// Writes the details of a single project in a machine-readable format.
+///
+/// JSON and TOML get the project's record on its own rather than wrapped in a list, including
+/// its `git` table, while CSV and TSV get the header and the project's rows.
+///
+/// # Arguments
+///
+/// * `info` - The project to write.
+/// * `format` - The output format; `Format::Text` is handled by the details view instead.
+pub fn print_project(info: &ProjectInfo, format: Format) {
+    match format {
+        Format::Json => println!("{}", to_json(&project_record(info, true))),
+        Format::Toml => print!("{}", toml::to_string(&project_record(info, true)).expect("Failed to serialise TOML")),
+        Format::Csv | Format::Tsv => print_projects(&[info], format, &[]),
+        Format::Text => {}
+    }
+}
+
+/// Prints the numbered project list as a table of the chosen columns.
+///
+/// Workspace members are listed under their workspace, indented and without a number.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to list, in listing order.
+/// * `columns` - The columns to show, from `COLUMNS`.
+pub fn print_table(projects: &[&ProjectInfo], columns: &[String]) {
+    let mut rows: Vec<Vec<String>> = vec![std::iter::once("#".to_string()).chain(columns.iter().map(|c| c.to_uppercase())).collect()];
+    for (index, info) in projects.iter().enumerate() {
+        let cells = columns.iter().map(|c| column_value(info, None, c));
+        rows.push(std::iter::once(format!("{}.", index + 1)).chain(cells).collect());
+        for member in &info.members {
+            let cells = columns.iter().map(|c| {
+                let value = column_value(member, Some(info), c);
+                if c == "name" { format!("- {}", value) } else { value }
+            });
+            rows.push(std::iter::once(String::new()).chain(cells).collect());
+        }
+    }
+
+    let mut widths = vec![0; columns.len() + 1];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, &width)| format!("{:<width$}", cell)).collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+/// Renders projects as CSV or TSV, one row per project and per workspace member.
+fn to_delimited(projects: &[&ProjectInfo], columns: &[String], separator: char) -> String {
+    let columns: Vec<String> = if columns.is_empty() {
+        COLUMNS.iter().map(|c| c.to_string()).collect()
+    } else {
+        columns.to_vec()
+    };
+
+    let mut out = String::new();
+    write_row(&mut out, columns.clone(), separator);
+    for info in projects {
+        write_row(&mut out, columns.iter().map(|c| column_value(info, None, c)).collect(), separator);
+        for member in &info.members {
+            write_row(&mut out, columns.iter().map(|c| column_value(member, Some(info), c)).collect(), separator);
+        }
+    }
+    out
+}
+
+/// Returns the text of one column for a project, or an empty string if it is not set.
+///
+/// List-valued fields are joined with `, `, and `publish` is `true`, `false` or the list of
+/// allowed registries.
+///
+/// # Arguments
+///
+/// * `info` - The project.
+/// * `workspace` - The workspace the project is a member of, for member rows.
+/// * `column` - The column name, from `COLUMNS`.
+pub fn column_value(info: &ProjectInfo, workspace: Option<&ProjectInfo>, column: &str) -> String {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    match column {
+        "name" => info.name.clone(),
+        "version" => text(&info.version),
+        "kind" => kind_name(info.kind).to_string(),
+        "path" => info.path.display().to_string(),
+        "root" => info.root.display().to_string(),
+        "workspace" => workspace.map(|w| w.path.display().to_string()).unwrap_or_default(),
+        "description" => text(&info.description),
+        "authors" => info.authors.join(", "),
+        "license" => text(&info.license),
+        "license-file" => text(&info.license_file),
+        "edition" => info.edition().unwrap_or_default().to_string(),
+        "rust-version" => text(&info.rust_version),
+        "repository" => text(&info.repository),
+        "homepage" => text(&info.homepage),
+        "documentation" => text(&info.documentation),
+        "readme" => text(&info.readme),
+        "keywords" => info.keywords.join(", "),
+        "categories" => info.categories.join(", "),
+        "publish" => match &info.publish {
+            None => "true".to_string(),
+            Some(registries) if registries.is_empty() => "false".to_string(),
+            Some(registries) => registries.join(", "),
+        },
+        "default-run" => text(&info.default_run),
+        "bins" => {
+            let bins: Vec<&str> = info.targets.iter().filter(|t| t.kind == TargetKind::Bin).map(|t| t.name.as_str()).collect();
+            bins.join(", ")
+        }
+        column if git::COLUMNS.contains(&column) => git_column(info, column),
+        _ => String::new(),
+    }
+}
+
+/// Returns the text of one of the `git::COLUMNS` for a project.
+///
+/// Projects outside a Git repository get `-` in the `branch` column and empty cells otherwise;
+/// a detached `HEAD` shows as `(detached)`.
+fn git_column(info: &ProjectInfo, column: &str) -> String {
+    let Some(status) = git::status(&info.path) else {
+        return if column == "branch" { "-".to_string() } else { String::new() };
+    };
+    let commit = status.last_commit.as_ref();
+    match column {
+        "branch" => status.branch.clone().unwrap_or_else(|| "(detached)".to_string()),
+        "upstream" => status.upstream.clone().unwrap_or_default(),
+        "ahead" => status.ahead.to_string(),
+        "behind" => status.behind.to_string(),
+        "changed" => status.changed.to_string(),
+        "untracked" => status.untracked.to_string(),
+        "stashes" => status.stashes.to_string(),
+        "last-commit" => commit.map(|c| util::format_time(c.time)).unwrap_or_default(),
+        "author" => commit.map(|c| c.author.clone()).unwrap_or_default(),
+        "subject" => commit.map(|c| c.subject.clone()).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Appends one row of cells, quoting CSV cells and flattening TSV cells as needed.
+///
+/// CSV cells containing a separator, quote or line break are quoted with `"`, doubling any
+/// quotes inside. TSV has no quoting, so tabs and line breaks in TSV cells become spaces.
+fn write_row(out: &mut String, cells: Vec<String>, separator: char) {
+    let cells: Vec<String> = cells
+        .into_iter()
+        .map(|cell| {
+            if separator == '\t' {
+                cell.replace(['\t', '\n', '\r'], " ")
+            } else if cell.contains([separator, '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect();
+    out.push_str(&cells.join(&separator.to_string()));
+    out.push('\n');
+}
+
+/// Renders a TOML value as JSON.
+///
+/// Tables become objects and arrays become arrays; datetimes are written as strings and
+/// floats that are not finite as `null`.
+pub fn to_json(value: &Value) -> String {
+    json_value(value).to_string()
+}
+
+/// Converts a TOML value into the equivalent JSON value.
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        Value::Array(items) => serde_json::Value::Array(items.iter().map(json_value).collect()),
+        Value::Table(table) => serde_json::Value::Object(table.iter().map(|(key, item)| (key.clone(), json_value(item))).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_output() {
+        let value: Value = "name = \"say \\\"hi\\\"\\n\\u0001\"\nsize = 3\nratio = 0.5\nbuilt = 2024-05-01T12:00:00Z\ntags = [\"a\", \"b\"]"
+            .parse()
+            .expect("valid TOML");
+        assert_eq!(
+            to_json(&value),
+            r#"{"built":"2024-05-01T12:00:00Z","name":"say \"hi\"\n\u0001","ratio":0.5,"size":3,"tags":["a","b"]}"#
+        );
+        assert_eq!(to_json(&Value::Float(f64::NAN)), "null");
+    }
+
+    #[test]
+    fn delimited_cells_are_escaped() {
+        let cells = || vec!["plain".to_string(), "a, b".to_string(), "say \"hi\"".to_string(), "two\nlines\tx".to_string()];
+        let mut csv = String::new();
+        write_row(&mut csv, cells(), ',');
+        assert_eq!(csv, "plain,\"a, b\",\"say \"\"hi\"\"\",\"two\nlines\tx\"\n");
+        let mut tsv = String::new();
+        write_row(&mut tsv, cells(), '\t');
+        assert_eq!(tsv, "plain\ta, b\tsay \"hi\"\ttwo lines x\n");
+    }
+}
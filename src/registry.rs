@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+
+/// The version of the index cache files Cargo writes that this reader understands.
+const CACHE_VERSION: u8 = 3;
+
+/// The directory names Cargo gives the crates.io index below `registry/index`: the sparse
+/// protocol's and the older Git index's.
+const CRATES_IO_PREFIXES: &[&str] = &["index.crates.io-", "github.com-"];
+
+/// A published version of a crate, as listed in the registry index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: Version,
+    /// Whether the version was yanked, so new lockfiles no longer pick it.
+    pub yanked: bool,
+}
+
+/// The local copies of the crates.io index that Cargo keeps, read without any network access.
+pub struct Index {
+    /// The index directories searched, in order.
+    pub dirs: Vec<PathBuf>,
+}
+
+impl Index {
+    /// Finds the index to read.
+    ///
+    /// # Arguments
+    ///
+    /// * `mirror` - A directory laid out like the crates.io index to read instead of Cargo's
+    ///   cache, e.g. a local mirror or a test fixture.
+    ///
+    /// # Returns
+    ///
+    /// The index, or a message if no index could be found. Without a mirror, every crates.io
+    /// index below `$CARGO_HOME/registry/index` is used.
+    pub fn open(mirror: Option<&Path>) -> Result<Index, String> {
+        if let Some(mirror) = mirror {
+            if !mirror.is_dir() {
+                return Err(format!("Registry index not found: {}", mirror.display()));
+            }
+            return Ok(Index { dirs: vec![mirror.to_path_buf()] });
+        }
+
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+            .ok_or("Could not find the Cargo home directory")?;
+        let root = cargo_home.join("registry").join("index");
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&root)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        CRATES_IO_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+                    })
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        if dirs.is_empty() {
+            return Err(format!("No crates.io index found in {}; run a cargo build once, or pass --index", root.display()));
+        }
+        Ok(Index { dirs })
+    }
+
+    /// Lists the published versions of a crate.
+    ///
+    /// Each index directory is searched for the crate's entry both in Cargo's binary cache
+    /// under `.cache/` and as a plain index file with one JSON object per line, and the
+    /// versions found are merged.
+    ///
+    /// # Returns
+    ///
+    /// The versions sorted oldest first, or `None` if no index has the crate.
+    pub fn releases(&self, name: &str) -> Option<Vec<Release>> {
+        let relative = entry_path(name);
+        let mut releases: Vec<Release> = Vec::new();
+        let mut found = false;
+        for dir in &self.dirs {
+            let cached = fs::read(dir.join(".cache").join(&relative)).ok().and_then(|bytes| cache_lines(&bytes));
+            let plain = fs::read_to_string(dir.join(&relative)).ok().map(|text| text.lines().map(String::from).collect());
+            for lines in [cached, plain].into_iter().flatten() {
+                found = true;
+                releases.extend(lines.iter().filter_map(|line| release(line)));
+            }
+        }
+        if !found {
+            return None;
+        }
+        releases.sort_by(|a, b| a.version.cmp(&b.version));
+        releases.dedup_by(|a, b| a.version == b.version);
+        Some(releases)
+    }
+}
+
+/// Returns the path of a crate's entry within an index, e.g. `se/rd/serde` or `3/s/syn`.
+fn entry_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => PathBuf::from("1").join(&name),
+        2 => PathBuf::from("2").join(&name),
+        3 => PathBuf::from("3").join(&name[..1]).join(&name),
+        _ => PathBuf::from(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+/// Reads the JSON lines out of one of Cargo's index cache files.
+///
+/// The file starts with a format version byte and a four-byte index version, followed by
+/// NUL-terminated fields: the HTTP cache header, then a version and its JSON line for
+/// every published version.
+fn cache_lines(bytes: &[u8]) -> Option<Vec<String>> {
+    if bytes.first() != Some(&CACHE_VERSION) || bytes.len() < 5 {
+        return None;
+    }
+    let mut fields = bytes[5..].split(|byte| *byte == 0);
+    fields.next()?;
+    let mut lines = Vec::new();
+    while let (Some(_version), Some(line)) = (fields.next(), fields.next()) {
+        if !line.is_empty() {
+            lines.push(String::from_utf8_lossy(line).into_owned());
+        }
+    }
+    Some(lines)
+}
+
+/// Reads the version and yanked flag from one JSON line of the index.
+fn release(line: &str) -> Option<Release> {
+    let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+    let version = Version::parse(entry.get("vers")?.as_str()?).ok()?;
+    let yanked = entry.get("yanked").and_then(serde_json::Value::as_bool).unwrap_or(false);
+    Some(Release { version, yanked })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_paths() {
+        assert_eq!(entry_path("a"), PathBuf::from("1/a"));
+        assert_eq!(entry_path("cc"), PathBuf::from("2/cc"));
+        assert_eq!(entry_path("syn"), PathBuf::from("3/s/syn"));
+        assert_eq!(entry_path("Serde"), PathBuf::from("se/rd/serde"));
+    }
+
+    #[test]
+    fn reads_cache_files() {
+        let mut bytes = vec![CACHE_VERSION, 2, 0, 0, 0];
+        bytes.extend_from_slice(b"etag: \"abc\"\0");
+        bytes.extend_from_slice(b"1.0.0\0{\"vers\":\"1.0.0\",\"yanked\":false}\0");
+        bytes.extend_from_slice(b"1.0.1\0{\"vers\":\"1.0.1\",\"yanked\":true}\0");
+        let lines = cache_lines(&bytes).expect("cache file is read");
+        let releases: Vec<Release> = lines.iter().filter_map(|line| release(line)).collect();
+        assert_eq!(
+            releases,
+            vec![
+                Release { version: Version::parse("1.0.0").unwrap(), yanked: false },
+                Release { version: Version::parse("1.0.1").unwrap(), yanked: true },
+            ]
+        );
+
+        bytes[0] = CACHE_VERSION + 1;
+        assert_eq!(cache_lines(&bytes), None, "other cache versions are not read");
+    }
+}
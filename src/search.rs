@@ -0,0 +1,181 @@
+use std::io::{self, IsTerminal};
+
+use crate::fuzzy;
+use crate::ProjectInfo;
+
+/// The part of a project that a search query matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Keyword,
+    Category,
+    Description,
+    Path,
+}
+
+impl Field {
+    /// How much a match in this field counts relative to the others.
+    ///
+    /// A match in the name is worth most, followed by keywords and categories, which are
+    /// chosen by the author to describe the crate.
+    fn weight(self) -> i64 {
+        match self {
+            Field::Name => 3,
+            Field::Keyword | Field::Category => 2,
+            Field::Description | Field::Path => 1,
+        }
+    }
+
+    /// Returns the label shown before a match in this field.
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Keyword => "keyword",
+            Field::Category => "category",
+            Field::Description => "description",
+            Field::Path => "path",
+        }
+    }
+}
+
+/// The best match of a query in one project.
+#[derive(Debug, Clone)]
+pub struct Hit<'a> {
+    /// The project that matched.
+    pub info: &'a ProjectInfo,
+    /// The weighted match score; higher is better.
+    pub score: i64,
+    /// The field that matched best.
+    pub field: Field,
+    /// The text of the field that matched, e.g. the keyword or path segment.
+    pub text: String,
+    /// The indices (in characters) of the matched characters in `text`.
+    pub positions: Vec<usize>,
+}
+
+/// Finds the field of a project that best matches a query.
+///
+/// The name, each keyword, each category, the description and each segment of the path
+/// below the scan root are matched separately, and the scores are weighted by field. On a
+/// tie, the field listed first wins.
+///
+/// # Arguments
+///
+/// * `info` - The project to match.
+/// * `query` - The fuzzy query.
+///
+/// # Returns
+///
+/// The best weighted match, or `None` if no field matches the query.
+pub fn best_match<'a>(info: &'a ProjectInfo, query: &str) -> Option<Hit<'a>> {
+    let relative = info.path.strip_prefix(&info.root).unwrap_or(&info.path);
+    let segments = relative.iter().map(|s| s.to_string_lossy().into_owned());
+
+    let candidates = std::iter::once((Field::Name, info.name.clone()))
+        .chain(info.keywords.iter().map(|k| (Field::Keyword, k.clone())))
+        .chain(info.categories.iter().map(|c| (Field::Category, c.clone())))
+        .chain(info.description.iter().map(|d| (Field::Description, d.clone())))
+        .chain(segments.map(|s| (Field::Path, s)));
+
+    candidates
+        .filter_map(|(field, text)| {
+            let found = fuzzy::fuzzy_match(query, &text)?;
+            Some(Hit { info, score: found.score * field.weight(), field, text, positions: found.positions })
+        })
+        .reduce(|best, hit| if hit.score > best.score { hit } else { best })
+}
+
+/// Ranks projects by how well they match a query, best match first.
+///
+/// Projects that do not match are left out. Ties are broken by name, then by path.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to search.
+/// * `query` - The fuzzy query.
+pub fn search<'a>(projects: &[&'a ProjectInfo], query: &str) -> Vec<Hit<'a>> {
+    let mut hits: Vec<Hit> = projects.iter().filter_map(|info| best_match(info, query)).collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.info.name.cmp(&b.info.name))
+            .then_with(|| a.info.path.cmp(&b.info.path))
+    });
+    hits
+}
+
+/// Returns `true` if matched characters should be highlighted with terminal escape codes.
+///
+/// Highlighting is used when stdout is a terminal and `NO_COLOR` is not set.
+pub fn use_highlight() -> bool {
+    io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Marks the characters at the given positions as bold and underlined.
+///
+/// # Arguments
+///
+/// * `text` - The text to highlight.
+/// * `positions` - The indices (in characters) of the characters to highlight.
+/// * `enabled` - Whether to add escape codes at all; the text is returned unchanged if not.
+pub fn highlight(text: &str, positions: &[usize], enabled: bool) -> String {
+    if !enabled || positions.is_empty() {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut highlighted = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.contains(&i);
+        if matched != highlighted {
+            out.push_str(if matched { "\x1b[1;4m" } else { "\x1b[0m" });
+            highlighted = matched;
+        }
+        out.push(c);
+    }
+    if highlighted {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn project(name: &str, keywords: &[&str], description: &str, path: &str) -> ProjectInfo {
+        ProjectInfo {
+            name: name.into(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            description: Some(description.into()),
+            path: PathBuf::from("/code").join(path),
+            root: PathBuf::from("/code"),
+            ..ProjectInfo::default()
+        }
+    }
+
+    #[test]
+    fn names_outrank_other_fields() {
+        let by_name = project("parser", &[], "Reads things", "tools/parser");
+        let by_keyword = project("syntax", &["parser"], "Reads things", "tools/syntax");
+        let by_description = project("reader", &[], "A parser for things", "tools/reader");
+        let by_path = project("misc", &[], "Other things", "parser/misc");
+        let unrelated = project("web", &["http"], "Serves pages", "apps/web");
+        let projects = [&by_path, &unrelated, &by_description, &by_keyword, &by_name];
+
+        let hits = search(&projects, "parser");
+        let ranked: Vec<(&str, Field)> = hits.iter().map(|hit| (hit.info.name.as_str(), hit.field)).collect();
+        // The description and the path tie, and ties go by name
+        assert_eq!(ranked, [("parser", Field::Name), ("syntax", Field::Keyword), ("misc", Field::Path), ("reader", Field::Description)]);
+        assert_eq!(hits[1].text, "parser");
+        assert_eq!(hits[2].positions, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn the_scan_root_is_not_searched() {
+        let info = project("app", &[], "", "app");
+        assert!(best_match(&info, "code").is_none());
+        assert_eq!(best_match(&info, "app").map(|hit| hit.field), Some(Field::Name), "a match in the name outweighs one in the path");
+    }
+}
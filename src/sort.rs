@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::{artifacts, disk, git, util, ProjectInfo};
+
+/// The orders the project list can be sorted in with `--sort` or the `sort` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// By package name, A to Z.
+    Name,
+    /// By the last time a file of the project changed, newest first.
+    Mtime,
+    /// By disk space, including the target directory, largest first.
+    Size,
+    /// By package version, highest first.
+    Version,
+    /// By Rust edition, oldest first.
+    Edition,
+    /// By the date of the last Git commit, newest first.
+    LastCommit,
+    /// By the number of non-blank lines in `.rs` files, largest first.
+    Loc,
+}
+
+impl SortKey {
+    /// The names accepted by `--sort`, in the same order as the variants.
+    pub const NAMES: &'static [&'static str] = &["name", "mtime", "size", "version", "edition", "last-commit", "loc"];
+
+    /// Returns `true` if the natural order for this key is largest or newest first.
+    fn descending(self) -> bool {
+        matches!(self, SortKey::Mtime | SortKey::Size | SortKey::Version | SortKey::LastCommit | SortKey::Loc)
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "mtime" => Ok(SortKey::Mtime),
+            "size" => Ok(SortKey::Size),
+            "version" => Ok(SortKey::Version),
+            "edition" => Ok(SortKey::Edition),
+            "last-commit" => Ok(SortKey::LastCommit),
+            "loc" => Ok(SortKey::Loc),
+            _ => Err(format!("Unknown sort order `{}`; expected one of: {}", s, SortKey::NAMES.join(", "))),
+        }
+    }
+}
+
+/// The value a project is sorted by; a key only ever produces one kind of value.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Text(String),
+    Number(u64),
+    Version(Vec<u64>),
+    Semver(semver::Version),
+    Time(SystemTime),
+}
+
+/// Sorts projects in place.
+///
+/// Each key has a natural direction (see `SortKey`), which `reverse` turns around. Projects
+/// without a value for the key, e.g. without a Git commit, always come last. Ties are
+/// broken by name, then by path. Keys that need to read the disk are computed once per project.
+///
+/// # Arguments
+///
+/// * `projects` - The projects to sort.
+/// * `key` - The sort order.
+/// * `reverse` - Whether to reverse the natural direction of the key.
+pub fn sort(projects: &mut Vec<&ProjectInfo>, key: SortKey, reverse: bool) {
+    let mut keyed: Vec<(Option<SortValue>, &ProjectInfo)> = projects.iter().map(|info| (sort_value(info, key), *info)).collect();
+    keyed.sort_by(|(a, info_a), (b, info_b)| {
+        let by_value = match (a, b) {
+            (Some(a), Some(b)) => {
+                let ordering = a.cmp(b);
+                if key.descending() != reverse { ordering.reverse() } else { ordering }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_value.then_with(|| info_a.name.cmp(&info_b.name)).then_with(|| info_a.path.cmp(&info_b.path))
+    });
+    *projects = keyed.into_iter().map(|(_, info)| info).collect();
+}
+
+/// Computes the value a project is sorted by, or `None` if it has none.
+fn sort_value(info: &ProjectInfo, key: SortKey) -> Option<SortValue> {
+    match key {
+        SortKey::Name => Some(SortValue::Text(info.name.clone())),
+        SortKey::Mtime => last_modified(info).map(SortValue::Time),
+        SortKey::Size => Some(SortValue::Number(size(info))),
+        SortKey::Version => info.version.as_deref().and_then(|v| semver::Version::parse(v.trim()).ok()).map(SortValue::Semver),
+        SortKey::Edition => info.edition().map(|e| SortValue::Version(util::version_key(e))),
+        SortKey::LastCommit => git::status(&info.path)?.last_commit.map(|c| SortValue::Time(c.time)),
+        SortKey::Loc => Some(SortValue::Number(lines_of_code(info))),
+    }
+}
+
+/// Returns the last time any file of a project or its workspace members was modified.
+///
+/// See `artifacts::newest_source` for which files count.
+pub fn last_modified(info: &ProjectInfo) -> Option<SystemTime> {
+    std::iter::once(info)
+        .chain(&info.members)
+        .filter_map(|project| artifacts::newest_source(&project.path))
+        .max()
+}
+
+/// Returns the disk space a project directory takes, including its `target/` directory.
+pub fn size(info: &ProjectInfo) -> u64 {
+    disk::measure(&info.path, &mut HashSet::new()).0
+}
+
+/// Counts the non-blank lines in the `.rs` files of a project and its workspace members.
+///
+/// Hidden directories, `target/` and nested packages that are not members are skipped.
+pub fn lines_of_code(info: &ProjectInfo) -> u64 {
+    let mut lines = 0;
+    count_lines(&info.path, true, &mut lines);
+    for member in &info.members {
+        count_lines(&member.path, true, &mut lines);
+    }
+    lines
+}
+
+/// Walks a directory for `lines_of_code`, adding to the running count.
+fn count_lines(dir: &Path, is_package_root: bool, lines: &mut u64) {
+    if !is_package_root && dir.join("Cargo.toml").is_file() {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if name != "target" && !name.starts_with('.') {
+                count_lines(&path, false, lines);
+            }
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            if let Ok(text) = fs::read_to_string(&path) {
+                *lines += text.lines().filter(|line| !line.trim().is_empty()).count() as u64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_sort_in_semver_order() {
+        let project = |name: &str, version: Option<&str>| ProjectInfo {
+            name: name.to_string(),
+            version: version.map(String::from),
+            ..ProjectInfo::default()
+        };
+        let infos = [
+            project("a", Some("1.0.0-rc.1")),
+            project("b", Some("1.0.0")),
+            project("c", None),
+            project("d", Some("1.10.0")),
+            project("e", Some("1.9.0")),
+            project("f", Some("1.0.0-alpha")),
+        ];
+        let mut projects: Vec<&ProjectInfo> = infos.iter().collect();
+        sort(&mut projects, SortKey::Version, false);
+        let names: Vec<&str> = projects.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, ["d", "e", "b", "a", "f", "c"]);
+
+        sort(&mut projects, SortKey::Version, true);
+        let names: Vec<&str> = projects.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, ["f", "a", "b", "e", "d", "c"]);
+    }
+}
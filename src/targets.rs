@@ -0,0 +1,276 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+use crate::workspace;
+
+/// The kind of a Cargo build target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Example,
+    Test,
+    Bench,
+}
+
+impl TargetKind {
+    /// The manifest table that declares targets of this kind, e.g. `[[bin]]`.
+    fn table(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+        }
+    }
+
+    /// The manifest key that turns off auto-discovery of this kind, e.g. `autobins`.
+    fn auto_key(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "autolib",
+            TargetKind::Bin => "autobins",
+            TargetKind::Example => "autoexamples",
+            TargetKind::Test => "autotests",
+            TargetKind::Bench => "autobenches",
+        }
+    }
+
+    /// The directory that targets of this kind are discovered in.
+    fn directory(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "src",
+            TargetKind::Bin => "src/bin",
+            TargetKind::Example => "examples",
+            TargetKind::Test => "tests",
+            TargetKind::Bench => "benches",
+        }
+    }
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.table())
+    }
+}
+
+/// A build target of a package: its library, a binary, an example, a test or a bench.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// What kind of target this is.
+    pub kind: TargetKind,
+    /// The target name, which is also the name of the produced binary.
+    pub name: String,
+    /// The path of the target's root source file, relative to the package directory.
+    pub path: PathBuf,
+    /// Whether the target was declared in the manifest rather than auto-discovered.
+    pub declared: bool,
+}
+
+/// Works out every build target of a package the way Cargo does.
+///
+/// Targets declared in `[lib]`, `[[bin]]`, `[[example]]`, `[[test]]` and `[[bench]]` are
+/// combined with the ones Cargo discovers on its own: `src/lib.rs`, `src/main.rs`,
+/// `src/bin/*.rs`, `src/bin/*/main.rs`, and `*.rs` or `*/main.rs` in `examples/`, `tests/`
+/// and `benches/`. A discovered target is dropped when a declared one has the same name or
+/// path, and discovery of a kind is skipped when its `auto*` key is `false`.
+///
+/// # Arguments
+///
+/// * `package` - The `[package]` table.
+/// * `manifest` - The parsed `Cargo.toml`.
+/// * `dir` - The package directory.
+///
+/// # Returns
+///
+/// The targets, library first, then binaries, examples, tests and benches, each sorted by name.
+pub fn discover(package: &Value, manifest: &Value, dir: &Path) -> Vec<Target> {
+    let package_name = package.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+    let mut targets = Vec::new();
+
+    for kind in [TargetKind::Lib, TargetKind::Bin, TargetKind::Example, TargetKind::Test, TargetKind::Bench] {
+        let declared = declared_targets(manifest, kind, dir, package_name);
+        let auto = package.get(kind.auto_key()).and_then(|a| a.as_bool()).unwrap_or(true);
+        let mut found: Vec<Target> = if auto && (kind != TargetKind::Lib || declared.is_empty()) {
+            discovered_targets(kind, dir, package_name)
+                .into_iter()
+                .filter(|t| !declared.iter().any(|d| d.name == t.name || d.path == t.path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        found.extend(declared);
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        targets.extend(found);
+    }
+    targets
+}
+
+/// Reads the targets of one kind declared in the manifest, filling in Cargo's default names and paths.
+fn declared_targets(manifest: &Value, kind: TargetKind, dir: &Path, package_name: &str) -> Vec<Target> {
+    let tables: Vec<&Value> = match manifest.get(kind.table()) {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(table @ Value::Table(_)) => vec![table],
+        _ => Vec::new(),
+    };
+
+    tables
+        .into_iter()
+        .filter_map(|table| {
+            let name = match table.get("name").and_then(|n| n.as_str()) {
+                Some(name) => name.to_string(),
+                None if kind == TargetKind::Lib => package_name.replace('-', "_"),
+                None => return None,
+            };
+            let path = match table.get("path").and_then(|p| p.as_str()) {
+                Some(path) => PathBuf::from(path),
+                None => default_path(kind, &name, dir, package_name),
+            };
+            Some(Target { kind, name, path, declared: true })
+        })
+        .collect()
+}
+
+/// Returns the source path Cargo assumes for a declared target without a `path`.
+fn default_path(kind: TargetKind, name: &str, dir: &Path, package_name: &str) -> PathBuf {
+    match kind {
+        TargetKind::Lib => PathBuf::from("src/lib.rs"),
+        TargetKind::Bin if name == package_name && dir.join("src/main.rs").is_file() => PathBuf::from("src/main.rs"),
+        _ => {
+            let file = Path::new(kind.directory()).join(format!("{}.rs", name));
+            if dir.join(&file).is_file() {
+                file
+            } else {
+                Path::new(kind.directory()).join(name).join("main.rs")
+            }
+        }
+    }
+}
+
+/// Finds the targets of one kind that Cargo would discover from the file layout.
+fn discovered_targets(kind: TargetKind, dir: &Path, package_name: &str) -> Vec<Target> {
+    let target = |name: &str, path: PathBuf| Target { kind, name: name.to_string(), path, declared: false };
+
+    match kind {
+        TargetKind::Lib => {
+            let path = PathBuf::from("src/lib.rs");
+            if dir.join(&path).is_file() {
+                vec![target(&package_name.replace('-', "_"), path)]
+            } else {
+                Vec::new()
+            }
+        }
+        _ => {
+            let mut found = Vec::new();
+            if kind == TargetKind::Bin && dir.join("src/main.rs").is_file() {
+                found.push(target(package_name, PathBuf::from("src/main.rs")));
+            }
+            let relative = Path::new(kind.directory());
+            let Ok(entries) = fs::read_dir(dir.join(relative)) else {
+                return found;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                if path.is_file() && path.extension().is_some_and(|e| e == "rs") {
+                    let name = file_name.trim_end_matches(".rs");
+                    found.push(target(name, relative.join(&file_name)));
+                } else if path.join("main.rs").is_file() {
+                    found.push(target(&file_name, relative.join(&file_name).join("main.rs")));
+                }
+            }
+            found
+        }
+    }
+}
+
+/// Returns the directory Cargo writes build artifacts of a package to.
+///
+/// Members of a workspace share the `target/` directory at the workspace root.
+pub fn target_dir(package_dir: &Path) -> PathBuf {
+    workspace::enclosing_root(package_dir)
+        .unwrap_or_else(|| package_dir.to_path_buf())
+        .join("target")
+}
+
+/// Returns the path of the executable Cargo produces for a binary or example target.
+///
+/// # Arguments
+///
+/// * `target_dir` - The target directory, e.g. `<workspace>/target`.
+/// * `profile` - The profile directory name, `debug` or `release`.
+/// * `target` - The target; only binaries and examples produce a runnable executable.
+///
+/// # Returns
+///
+/// The executable path, or `None` for libraries, tests and benches.
+pub fn executable_path(target_dir: &Path, profile: &str, target: &Target) -> Option<PathBuf> {
+    let file = format!("{}{}", target.name, std::env::consts::EXE_SUFFIX);
+    match target.kind {
+        TargetKind::Bin => Some(target_dir.join(profile).join(file)),
+        TargetKind::Example => Some(target_dir.join(profile).join("examples").join(file)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    #[test]
+    fn declared_and_discovered_targets() {
+        let dir = scratch_dir("targets-discover");
+        for file in ["src/lib.rs", "src/main.rs", "src/bin/tool.rs", "src/bin/multi/main.rs", "src/bin/multi/args.rs", "examples/demo.rs", "examples/custom.rs", "tests/it.rs", "benches/speed.rs"] {
+            write_file(&dir.join(file), "\n");
+        }
+        let manifest: Value = r#"
+            [package]
+            name = "my-app"
+            autobenches = false
+
+            [[example]]
+            name = "renamed"
+            path = "examples/custom.rs"
+
+            [[bin]]
+            name = "extra"
+            path = "scripts/extra.rs"
+        "#
+        .parse()
+        .expect("valid manifest");
+        let package = manifest.get("package").expect("package table");
+        let targets = discover(package, &manifest, &dir);
+        let _ = fs::remove_dir_all(&dir);
+
+        let listed: Vec<String> = targets
+            .iter()
+            .map(|t| format!("{} {} {}{}", t.kind, t.name, t.path.display(), if t.declared { " (declared)" } else { "" }))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                "lib my_app src/lib.rs",
+                "bin extra scripts/extra.rs (declared)",
+                "bin multi src/bin/multi/main.rs",
+                "bin my-app src/main.rs",
+                "bin tool src/bin/tool.rs",
+                "example demo examples/demo.rs",
+                "example renamed examples/custom.rs (declared)",
+                "test it tests/it.rs",
+            ]
+        );
+    }
+
+    #[test]
+    fn executable_paths() {
+        let target = |kind: TargetKind| Target { kind, name: "app".into(), path: PathBuf::new(), declared: false };
+        let suffix = std::env::consts::EXE_SUFFIX;
+        let dir = Path::new("/ws/target");
+        assert_eq!(executable_path(dir, "release", &target(TargetKind::Bin)), Some(dir.join(format!("release/app{}", suffix))));
+        assert_eq!(executable_path(dir, "debug", &target(TargetKind::Example)), Some(dir.join(format!("debug/examples/app{}", suffix))));
+        assert_eq!(executable_path(dir, "debug", &target(TargetKind::Lib)), None);
+    }
+}
@@ -0,0 +1,589 @@
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+use crate::{cargo, display_name, duplicate_names, project_details, search, watch, with_members, ProjectInfo};
+
+/// How long to wait for the rest of an escape sequence before treating `Esc` as a key press.
+const ESCAPE_TIMEOUT_MS: i32 = 50;
+
+/// The help line shown at the bottom of the screen.
+const HELP: &str = "↑/↓ move  / filter  s sort  r reverse  o open  b build  x run  t test  c check  l clippy  d doc  q quit";
+
+/// The keys that run a cargo command on the selected project, and the command each one runs.
+const CARGO_KEYS: &[(char, &str)] = &[('b', "build"), ('x', "run"), ('t', "test"), ('c', "check"), ('l', "clippy"), ('d', "doc")];
+
+/// The orders the project list can be sorted in, cycled with `s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    /// Best fuzzy match first while filtering, in listing order otherwise, e.g. as set by `--sort`.
+    Match,
+    /// By package name.
+    Name,
+    /// By project path.
+    Path,
+    /// By package version, comparing numeric components as numbers.
+    Version,
+}
+
+impl SortKey {
+    /// Returns the sort order that follows this one when `s` is pressed.
+    fn next(self) -> Self {
+        match self {
+            SortKey::Match => SortKey::Name,
+            SortKey::Name => SortKey::Path,
+            SortKey::Path => SortKey::Version,
+            SortKey::Version => SortKey::Match,
+        }
+    }
+
+    /// Returns the name shown in the header.
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Match => "match",
+            SortKey::Name => "name",
+            SortKey::Path => "path",
+            SortKey::Version => "version",
+        }
+    }
+}
+
+/// A key press read from the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    CtrlC,
+    /// Not a key: the watched directories changed.
+    Update,
+    Other,
+}
+
+/// Returns `true` if the full-screen browser can run, i.e. stdin and stdout are terminals.
+pub fn is_available() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal() && std::env::var("TERM").map_or(true, |t| t != "dumb")
+}
+
+/// Runs the full-screen project browser until the user quits.
+///
+/// The browser shows a scrollable list of the projects and their workspace members next to a
+/// details pane for the selected project. Typing after `/` filters the list by fuzzy match on
+/// name, keywords, categories, description and path, and the selected project can be opened
+/// in `$VISUAL`/`$EDITOR`, or built, run, tested, checked, linted with Clippy or documented
+/// without leaving the tool. With a live list, projects that are added, removed or edited
+/// while the browser runs show up without restarting it.
+///
+/// # Arguments
+///
+/// * `listed` - The projects to browse, in listing order.
+/// * `filter` - The initial filter text, e.g. the query passed to `find`.
+/// * `live` - Keeps the list up to date with the filesystem, if watching could be started.
+pub fn run(listed: &[&ProjectInfo], filter: &str, mut live: Option<watch::LiveList>) {
+    let mut browser = Browser::new(listed);
+    browser.set_filter(filter.to_string());
+    let mut terminal = match RawTerminal::enter() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Could not start the terminal UI: {}", e);
+            return;
+        }
+    };
+    let mut keys = KeyReader { watch_fd: live.as_ref().map(|live| live.fd()), ..KeyReader::default() };
+
+    loop {
+        browser.draw(terminal_size());
+        let Some(key) = keys.read() else {
+            break;
+        };
+        if key == Key::Update {
+            if let Some((projects, summary)) = live.as_mut().and_then(|live| live.update()) {
+                browser.replace(&projects.iter().collect::<Vec<_>>());
+                browser.status = format!("Projects updated: {}", summary);
+            }
+            continue;
+        }
+
+        if browser.editing_filter {
+            match key {
+                Key::Char(c) => browser.set_filter(format!("{}{}", browser.filter, c)),
+                Key::Backspace => {
+                    let mut filter = browser.filter.clone();
+                    filter.pop();
+                    browser.set_filter(filter);
+                }
+                Key::Enter => browser.editing_filter = false,
+                Key::Escape => {
+                    browser.editing_filter = false;
+                    browser.set_filter(String::new());
+                }
+                Key::CtrlC => break,
+                _ => browser.navigate(key),
+            }
+            continue;
+        }
+
+        let cargo_command = CARGO_KEYS.iter().find(|(c, _)| key == Key::Char(*c)).map(|(_, command)| *command);
+        if let Some(command) = cargo_command {
+            if let Some(path) = browser.current().map(|info| info.path.clone()) {
+                browser.status = run_outside(&mut terminal, &path, "cargo", &[command]);
+            }
+            continue;
+        }
+
+        match key {
+            Key::Char('q') | Key::CtrlC => break,
+            Key::Escape if !browser.filter.is_empty() => browser.set_filter(String::new()),
+            Key::Char('/') => browser.editing_filter = true,
+            Key::Char('s') => {
+                browser.sort = browser.sort.next();
+                browser.refresh();
+            }
+            Key::Char('r') => {
+                browser.reverse = !browser.reverse;
+                browser.refresh();
+            }
+            Key::Char('o') | Key::Enter => {
+                if let Some(path) = browser.current().map(|info| info.path.clone()) {
+                    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+                    browser.status = run_outside(&mut terminal, &path, &editor, &["."]);
+                }
+            }
+            _ => browser.navigate(key),
+        }
+    }
+}
+
+/// Leaves the full-screen view, runs a program in a project directory and comes back.
+///
+/// The program inherits the terminal. After it exits the user is asked to press Enter, so its
+/// output can be read before the browser is redrawn.
+///
+/// # Returns
+///
+/// A status line describing how the program exited.
+fn run_outside(terminal: &mut RawTerminal, dir: &Path, program: &str, args: &[&str]) -> String {
+    terminal.suspend();
+    let command_line = format!("{} {}", program, args.join(" "));
+    println!("$ {}  (in {})", command_line, dir.display());
+
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let status = match cargo::run(dir, program, &args) {
+        Ok(status) if status.success() => format!("`{}` finished successfully", command_line),
+        Ok(status) => format!("`{}` failed (exit code {})", command_line, cargo::exit_code(status)),
+        Err(message) => message,
+    };
+
+    print!("\n{}. Press Enter to return...", status);
+    io::stdout().flush().ok();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).ok();
+
+    terminal.resume();
+    status
+}
+
+/// The state of the project browser.
+struct Browser {
+    /// Every project that can be browsed, with workspace members after their workspace.
+    projects: Vec<ProjectInfo>,
+    /// The name each project is listed under, disambiguated where names are shared.
+    labels: Vec<String>,
+    /// Indices into `projects` of the rows currently shown, in display order.
+    visible: Vec<usize>,
+    /// The selected row, as an index into `visible`.
+    selected: usize,
+    /// The first row shown in the list pane, as an index into `visible`.
+    offset: usize,
+    /// The current filter text.
+    filter: String,
+    /// Whether key presses are currently typed into the filter.
+    editing_filter: bool,
+    /// The current sort order.
+    sort: SortKey,
+    /// Whether the sort order is reversed.
+    reverse: bool,
+    /// A message shown above the help line, e.g. the result of the last build.
+    status: String,
+}
+
+impl Browser {
+    /// Creates a browser over the given projects and their workspace members.
+    fn new(listed: &[&ProjectInfo]) -> Self {
+        let mut browser = Browser {
+            projects: Vec::new(),
+            labels: Vec::new(),
+            visible: Vec::new(),
+            selected: 0,
+            offset: 0,
+            filter: String::new(),
+            editing_filter: false,
+            sort: SortKey::Match,
+            reverse: false,
+            status: String::new(),
+        };
+        browser.replace(listed);
+        browser
+    }
+
+    /// Replaces the projects being browsed, keeping the filter, sort order and selection.
+    fn replace(&mut self, listed: &[&ProjectInfo]) {
+        let selected_path = self.current().map(|info| info.path.clone());
+        self.projects = with_members(listed.iter().copied()).into_iter().cloned().collect();
+
+        let duplicates = duplicate_names(self.projects.iter());
+        self.labels = self
+            .projects
+            .iter()
+            .map(|info| {
+                let kind = if info.is_workspace() { " [workspace]" } else { "" };
+                format!("{}{}", display_name(info, &duplicates), kind)
+            })
+            .collect();
+        self.refresh_keeping(selected_path);
+    }
+
+    /// Returns the selected project, if any row is shown.
+    fn current(&self) -> Option<&ProjectInfo> {
+        self.visible.get(self.selected).map(|&i| &self.projects[i])
+    }
+
+    /// Replaces the filter text and recomputes the visible rows.
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.refresh();
+    }
+
+    /// Recomputes the visible rows from the filter and sort order, keeping the selection
+    /// on the same project when it is still shown.
+    fn refresh(&mut self) {
+        self.refresh_keeping(self.current().map(|info| info.path.clone()));
+    }
+
+    /// Recomputes the visible rows, selecting the project at the given path if it is shown.
+    fn refresh_keeping(&mut self, selected_path: Option<std::path::PathBuf>) {
+        let mut rows: Vec<(usize, i64)> = self
+            .projects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, info)| filter_score(info, &self.filter).map(|score| (i, score)))
+            .collect();
+
+        let projects = &self.projects;
+        rows.sort_by(|&(a, score_a), &(b, score_b)| {
+            let (a, b) = (&projects[a], &projects[b]);
+            let by_name = a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path));
+            match self.sort {
+                SortKey::Match => score_b.cmp(&score_a),
+                SortKey::Name => by_name,
+                SortKey::Path => a.path.cmp(&b.path),
+                SortKey::Version => {
+                    let version = |info: &ProjectInfo| info.version.as_deref().and_then(|v| semver::Version::parse(v.trim()).ok());
+                    version(a).cmp(&version(b)).then(by_name)
+                }
+            }
+        });
+        if self.reverse {
+            rows.reverse();
+        }
+
+        self.visible = rows.into_iter().map(|(i, _)| i).collect();
+        self.selected = selected_path
+            .and_then(|path| self.visible.iter().position(|&i| self.projects[i].path == path))
+            .unwrap_or(0);
+    }
+
+    /// Moves the selection for navigation keys; other keys are ignored.
+    fn navigate(&mut self, key: Key) {
+        let page = terminal_size().1.saturating_sub(4).max(1);
+        let last = self.visible.len().saturating_sub(1);
+        self.selected = match key {
+            Key::Up | Key::Char('k') => self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => (self.selected + 1).min(last),
+            Key::PageUp => self.selected.saturating_sub(page),
+            Key::PageDown => (self.selected + page).min(last),
+            Key::Home | Key::Char('g') => 0,
+            Key::End | Key::Char('G') => last,
+            _ => self.selected,
+        };
+    }
+
+    /// Draws the whole screen: header, list pane, details pane, status and help lines.
+    fn draw(&mut self, (width, height): (usize, usize)) {
+        let list_width = (width / 2).clamp(20, 48).min(width);
+        let details_width = width.saturating_sub(list_width + 3);
+        let rows = height.saturating_sub(3);
+
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if rows > 0 && self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+
+        let mut screen = String::from("\x1b[H");
+        let filter = if self.editing_filter || !self.filter.is_empty() {
+            format!("  filter: /{}{}", self.filter, if self.editing_filter { "_" } else { "" })
+        } else {
+            String::new()
+        };
+        let header = format!(
+            " My Rust Manager  {}/{} projects  sort: {}{}{}",
+            self.visible.len(),
+            self.projects.len(),
+            self.sort.label(),
+            if self.reverse { " (reversed)" } else { "" },
+            filter
+        );
+        screen.push_str(&format!("\x1b[7m{}\x1b[0m\r\n", fit(&header, width)));
+
+        let details = self.current().map(project_details).unwrap_or_default();
+        for row in 0..rows {
+            let index = self.offset + row;
+            let entry = match self.visible.get(index) {
+                Some(&i) => fit(&format!(" {}", self.labels[i]), list_width),
+                None => fit("", list_width),
+            };
+            if index == self.selected && !self.visible.is_empty() {
+                screen.push_str(&format!("\x1b[7m{}\x1b[0m", entry));
+            } else {
+                screen.push_str(&entry);
+            }
+            let detail = details.get(row).map(String::as_str).unwrap_or("");
+            screen.push_str(&format!(" │ {}\x1b[K\r\n", fit(detail, details_width)));
+        }
+
+        screen.push_str(&format!("{}\x1b[K\r\n", fit(&self.status, width)));
+        screen.push_str(&format!("\x1b[2m{}\x1b[0m\x1b[K", fit(HELP, width)));
+
+        let mut stdout = io::stdout();
+        stdout.write_all(screen.as_bytes()).ok();
+        stdout.flush().ok();
+    }
+}
+
+/// Scores a project against the filter, or returns `None` if it does not match.
+///
+/// The score is that of the best-matching field, as ranked by `find`.
+fn filter_score(info: &ProjectInfo, filter: &str) -> Option<i64> {
+    if filter.trim().is_empty() {
+        return Some(0);
+    }
+    search::best_match(info, filter).map(|hit| hit.score)
+}
+
+/// Truncates or pads a string with spaces to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().filter(|c| !c.is_control()).take(width).collect();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width - len));
+    fitted
+}
+
+/// Returns the terminal size as `(columns, rows)`, falling back to 80x24.
+fn terminal_size() -> (usize, usize) {
+    // SAFETY: `winsize` is plain data, and TIOCGWINSZ only writes into the struct passed to it.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if ok && size.ws_col > 0 && size.ws_row > 0 {
+        (size.ws_col as usize, size.ws_row as usize)
+    } else {
+        (80, 24)
+    }
+}
+
+/// Puts the terminal into raw mode on the alternate screen, and restores it when dropped.
+struct RawTerminal {
+    /// The terminal settings from before raw mode was entered.
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    /// Saves the current terminal settings and switches to raw mode on the alternate screen.
+    fn enter() -> io::Result<Self> {
+        // SAFETY: `termios` is plain data that `tcgetattr` fills in completely on success.
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let terminal = RawTerminal { original };
+        terminal.resume();
+        Ok(terminal)
+    }
+
+    /// Switches (back) to raw mode on the alternate screen with the cursor hidden.
+    fn resume(&self) {
+        let mut raw = self.original;
+        // SAFETY: `raw` is a valid `termios` copied from the saved settings.
+        unsafe {
+            libc::cfmakeraw(&mut raw);
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+        }
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        io::stdout().flush().ok();
+    }
+
+    /// Restores the original terminal settings and leaves the alternate screen.
+    fn suspend(&self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        io::stdout().flush().ok();
+        // SAFETY: `original` holds the settings previously returned by `tcgetattr`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
+
+/// Reads key presses from stdin, decoding escape sequences and UTF-8.
+#[derive(Default)]
+struct KeyReader {
+    /// Bytes that were read but not yet decoded into keys.
+    pending: VecDeque<u8>,
+    /// A descriptor that yields `Key::Update` when it becomes readable, e.g. a watcher's.
+    watch_fd: Option<i32>,
+}
+
+impl KeyReader {
+    /// Blocks until a key is pressed, returning `None` once stdin is closed.
+    ///
+    /// A signal such as `SIGWINCH` interrupting the wait yields `Key::Other`, so the caller
+    /// redraws the screen at the new size, and the watched descriptor becoming readable
+    /// yields `Key::Update`.
+    fn read(&mut self) -> Option<Key> {
+        if self.pending.is_empty() {
+            match self.wait_for_input() {
+                Ok(true) => {}
+                Ok(false) => return Some(Key::Update),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Some(Key::Other),
+                Err(_) => return None,
+            }
+            match self.fill(-1) {
+                Ok(true) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => return Some(Key::Other),
+                Ok(false) | Err(_) => return None,
+            }
+        }
+        let byte = self.pending.pop_front()?;
+
+        Some(match byte {
+            0x1b => self.read_escape(),
+            b'\r' | b'\n' => Key::Enter,
+            0x7f | 0x08 => Key::Backspace,
+            0x03 => Key::CtrlC,
+            byte if byte < 0x20 => Key::Other,
+            byte => self.read_char(byte),
+        })
+    }
+
+    /// Decodes the rest of an escape sequence, or a lone `Esc` if nothing follows quickly.
+    fn read_escape(&mut self) -> Key {
+        if self.pending.is_empty() && !matches!(self.fill(ESCAPE_TIMEOUT_MS), Ok(true)) {
+            return Key::Escape;
+        }
+        if !matches!(self.pending.front(), Some(b'[') | Some(b'O')) {
+            return Key::Escape;
+        }
+        self.pending.pop_front();
+
+        let mut sequence = Vec::new();
+        loop {
+            if self.pending.is_empty() && !matches!(self.fill(ESCAPE_TIMEOUT_MS), Ok(true)) {
+                return Key::Other;
+            }
+            let byte = self.pending.pop_front().unwrap_or(b'~');
+            sequence.push(byte);
+            if byte.is_ascii_alphabetic() || byte == b'~' {
+                break;
+            }
+        }
+
+        match sequence.as_slice() {
+            b"A" => Key::Up,
+            b"B" => Key::Down,
+            b"H" | b"1~" | b"7~" => Key::Home,
+            b"F" | b"4~" | b"8~" => Key::End,
+            b"5~" => Key::PageUp,
+            b"6~" => Key::PageDown,
+            _ => Key::Other,
+        }
+    }
+
+    /// Decodes a UTF-8 character starting with the given byte.
+    fn read_char(&mut self, first: u8) -> Key {
+        let len = match first {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        let mut bytes = vec![first];
+        while bytes.len() < len {
+            if self.pending.is_empty() && !matches!(self.fill(ESCAPE_TIMEOUT_MS), Ok(true)) {
+                break;
+            }
+            bytes.extend(self.pending.pop_front());
+        }
+        std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .map_or(Key::Other, Key::Char)
+    }
+
+    /// Waits until stdin or the watched descriptor is readable.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if there is input on stdin, `Ok(false)` if only the watched descriptor is
+    /// readable, and the OS error if waiting failed.
+    fn wait_for_input(&self) -> io::Result<bool> {
+        let Some(watch_fd) = self.watch_fd else {
+            return Ok(true);
+        };
+        let mut polls = [
+            libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 },
+            libc::pollfd { fd: watch_fd, events: libc::POLLIN, revents: 0 },
+        ];
+        // SAFETY: `polls` points to exactly two valid `pollfd`s.
+        if unsafe { libc::poll(polls.as_mut_ptr(), 2, -1) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(polls[0].revents != 0 || polls[1].revents == 0)
+    }
+
+    /// Waits up to `timeout_ms` milliseconds (forever if negative) for input and reads it.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if any bytes were read, `Ok(false)` on timeout or end of input, and the
+    /// OS error if waiting or reading failed.
+    fn fill(&mut self, timeout_ms: i32) -> io::Result<bool> {
+        let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        // SAFETY: `poll` points to exactly one valid `pollfd`.
+        match unsafe { libc::poll(&mut poll, 1, timeout_ms) } {
+            0 => return Ok(false),
+            n if n < 0 => return Err(io::Error::last_os_error()),
+            _ => {}
+        }
+
+        let mut buffer = [0u8; 64];
+        // SAFETY: the buffer is valid for writes of `buffer.len()` bytes.
+        let read = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.pending.extend(&buffer[..read as usize]);
+        Ok(read > 0)
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Formats a byte count with a binary unit, e.g. `4.2 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Formats a point in time as a UTC date and time, e.g. `2024-05-01 13:45 UTC`.
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let minutes = seconds % 86_400 / 60;
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, minutes / 60, minutes % 60)
+}
+
+/// Formats a point in time as an RFC 3339 UTC timestamp, e.g. `2024-05-01T13:45:09Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let second_of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3_600,
+        second_of_day % 3_600 / 60,
+        second_of_day % 60
+    )
+}
+
+/// Formats how long ago a point in time was, e.g. `3 days ago`.
+pub fn format_age(time: SystemTime) -> String {
+    let Ok(age) = SystemTime::now().duration_since(time) else {
+        return "in the future".to_string();
+    };
+    let seconds = age.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        86_400..=2_591_999 => (seconds / 86_400, "day"),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Formats an elapsed time compactly, e.g. `850ms`, `12.3s` or `4m 05s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else if seconds >= 1 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Splits a version into its leading numeric components for comparison, e.g. `1.10.0-rc1` into `[1, 10, 0]`.
+///
+/// Versions compare component by component, so `1.10` sorts after `1.9`; an empty or
+/// non-numeric version gives an empty key, which sorts first.
+pub fn version_key(version: &str) -> Vec<u64> {
+    version.trim().split(['.', '-', '+']).map_while(|part| part.parse().ok()).collect()
+}
+
+/// Parses a duration written as a number and a unit, e.g. `30d`, `12h` or `2w`.
+///
+/// The units are `s`, `min`, `h`, `d`, `w`, `mo` (30 days) and `y` (365 days); a number on
+/// its own counts days.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| format!("Invalid duration `{}`; expected e.g. 30d", text))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "min" => 60,
+        "h" => 3_600,
+        "d" | "" => 86_400,
+        "w" => 604_800,
+        "mo" => 2_592_000,
+        "y" => 31_536_000,
+        _ => return Err(format!("Invalid duration unit in `{}`; expected s, min, h, d, w, mo or y", text)),
+    };
+    Ok(Duration::from_secs(number.saturating_mul(unit_seconds)))
+}
+
+/// Parses a size written as a number and an optional binary unit, e.g. `2G`, `500M` or `1.5GiB`.
+///
+/// The units `K`, `M`, `G` and `T` are powers of 1024 and may be followed by `B` or `iB`;
+/// a number on its own counts bytes.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("Invalid size `{}`; expected e.g. 2G", text))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let power = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return Err(format!("Invalid size unit in `{}`; expected K, M, G or T", text)),
+    };
+    Ok((number * 1024f64.powi(power)) as u64)
+}
+
+/// Converts a number of days since 1970-01-01 into a `(year, month, day)` date.
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm for the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Creates an empty scratch directory for a test, unique to the test and the process.
+#[cfg(test)]
+pub fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("my_rust-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create scratch directory");
+    dir
+}
+
+/// Writes a file for a test, creating its parent directories.
+#[cfg(test)]
+pub fn write_file(path: &std::path::Path, text: &str) {
+    std::fs::create_dir_all(path.parent().expect("file has a parent")).expect("create fixture directory");
+    std::fs::write(path, text).expect("write fixture file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_and_times_are_formatted() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_time(time), "2024-02-29 12:34 UTC");
+        assert_eq!(format_timestamp(time), "2024-02-29T12:34:56Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+
+        let ago = |seconds: u64| format_age(SystemTime::now() - Duration::from_secs(seconds));
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 86_400 + 10), "3 days ago");
+        assert_eq!(ago(2 * 31_536_000), "2 years ago");
+        assert_eq!(format_age(SystemTime::now() + Duration::from_secs(600)), "in the future");
+    }
+
+    #[test]
+    fn durations_and_sizes_are_parsed() {
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86_400)));
+        assert_eq!(parse_duration(" 12h "), Ok(Duration::from_secs(12 * 3_600)));
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90 * 86_400)), "a bare number counts days");
+        assert_eq!(parse_duration("2mo"), Ok(Duration::from_secs(2 * 2_592_000)));
+        assert_eq!(parse_duration("5min"), Ok(Duration::from_secs(300)));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3m").is_err_and(|e| e.contains("unit")));
+        assert!(parse_duration("-1d").is_err());
+
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert_eq!(parse_size("500 MB"), Ok(500 << 20));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("2X").is_err_and(|e| e.contains("unit")));
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::{btree_map, BTreeMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use toml::value::Table;
+use toml::Value;
+
+use crate::cache::Cache;
+use crate::{is_skipped_dir, output, util, walk, ProjectIndex, ProjectInfo, ScanOptions};
+
+pub use inotify::Watcher;
+
+/// How long the filesystem must stay quiet before a burst of changes is applied.
+const QUIET: Duration = Duration::from_millis(100);
+
+/// The longest a burst of changes is held back before it is applied anyway.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// The directories of a package, besides `src/` itself, where Cargo discovers targets from
+/// `*.rs` and `*/main.rs` files.
+const TARGET_DIRS: [&str; 4] = ["src/bin", "examples", "tests", "benches"];
+
+/// Something that happened below a watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notice {
+    /// An entry was created, deleted, moved or written to.
+    Entry {
+        /// The path of the entry; the watched directory itself if it was deleted or moved.
+        path: PathBuf,
+        /// Whether the entry is a directory.
+        is_dir: bool,
+    },
+    /// The kernel dropped notifications, so anything may have changed.
+    Overflow,
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::Notice;
+
+    /// The events a directory is watched for: entries appearing, disappearing or being written,
+    /// and the directory itself going away.
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF
+        | libc::IN_ONLYDIR;
+
+    /// Watches directories for changes with inotify.
+    #[derive(Debug)]
+    pub struct Watcher {
+        /// The inotify instance.
+        fd: OwnedFd,
+        /// The watched directories, by watch descriptor.
+        dirs: HashMap<i32, PathBuf>,
+        /// Whether the warning about the watch limit was printed.
+        warned: bool,
+    }
+
+    impl Watcher {
+        /// Creates an inotify instance without any watches.
+        pub fn new() -> Result<Watcher, String> {
+            // SAFETY: `inotify_init1` takes no pointers and returns a new descriptor or -1.
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(format!("Could not watch for changes: {}", io::Error::last_os_error()));
+            }
+            // SAFETY: `fd` is a freshly created descriptor that nothing else owns.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            Ok(Watcher { fd, dirs: HashMap::new(), warned: false })
+        }
+
+        /// Returns the descriptor that becomes readable when there are notices to read.
+        pub fn fd(&self) -> RawFd {
+            self.fd.as_raw_fd()
+        }
+
+        /// Starts watching a directory; watching it again is harmless.
+        ///
+        /// A directory that vanished in the meantime is skipped. When the system-wide limit
+        /// on watches is reached, a warning is printed once and further directories are not
+        /// watched.
+        pub fn add(&mut self, dir: &Path) {
+            let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                return;
+            };
+            // SAFETY: `path` is a valid NUL-terminated string for the duration of the call.
+            let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), MASK) };
+            if wd >= 0 {
+                self.dirs.insert(wd, dir.to_path_buf());
+            } else if io::Error::last_os_error().raw_os_error() == Some(libc::ENOSPC) && !self.warned {
+                self.warned = true;
+                eprintln!("Not every directory can be watched; raise the fs.inotify.max_user_watches limit to watch them all");
+            }
+        }
+
+        /// Waits for notices and reads them.
+        ///
+        /// # Arguments
+        ///
+        /// * `timeout` - How long to wait, or `None` to wait until something happens.
+        ///
+        /// # Returns
+        ///
+        /// The notices, which are empty if the wait timed out or was interrupted by a signal.
+        pub fn read(&mut self, timeout: Option<Duration>) -> Result<Vec<Notice>, String> {
+            let timeout = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32);
+            let mut poll = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            // SAFETY: `poll` points to exactly one valid `pollfd`.
+            match unsafe { libc::poll(&mut poll, 1, timeout) } {
+                0 => return Ok(Vec::new()),
+                n if n < 0 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        return Ok(Vec::new());
+                    }
+                    return Err(format!("Could not watch for changes: {}", error));
+                }
+                _ => {}
+            }
+
+            let mut buffer = vec![0u8; 64 * 1024];
+            // SAFETY: the buffer is valid for writes of `buffer.len()` bytes.
+            let read = unsafe { libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+            if read < 0 {
+                let error = io::Error::last_os_error();
+                if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted) {
+                    return Ok(Vec::new());
+                }
+                return Err(format!("Could not watch for changes: {}", error));
+            }
+
+            let mut notices = Vec::new();
+            let mut offset = 0;
+            let header = std::mem::size_of::<libc::inotify_event>();
+            while offset + header <= read as usize {
+                // SAFETY: the kernel wrote a whole `inotify_event` header at this offset; it may
+                // not be aligned in the byte buffer, hence the unaligned read.
+                let event: libc::inotify_event = unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+                let name_bytes = &buffer[offset + header..(offset + header + event.len as usize).min(read as usize)];
+                let name_len = name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len());
+                let name = std::ffi::OsStr::from_bytes(&name_bytes[..name_len]);
+                offset += header + event.len as usize;
+
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    notices.push(Notice::Overflow);
+                    continue;
+                }
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let Some(dir) = self.dirs.get(&event.wd) else {
+                    continue;
+                };
+                if name.is_empty() {
+                    notices.push(Notice::Entry { path: dir.clone(), is_dir: true });
+                } else {
+                    notices.push(Notice::Entry { path: dir.join(name), is_dir: event.mask & libc::IN_ISDIR != 0 });
+                }
+            }
+            Ok(notices)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod inotify {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::Notice;
+
+    /// Stands in for the inotify watcher on systems without inotify.
+    #[derive(Debug)]
+    pub struct Watcher;
+
+    impl Watcher {
+        /// Fails, because watching needs inotify.
+        pub fn new() -> Result<Watcher, String> {
+            Err("Watching for changes needs inotify, which is only available on Linux".to_string())
+        }
+
+        /// Returns an invalid descriptor.
+        pub fn fd(&self) -> i32 {
+            -1
+        }
+
+        /// Does nothing.
+        pub fn add(&mut self, _dir: &Path) {}
+
+        /// Never returns any notices.
+        pub fn read(&mut self, _timeout: Option<Duration>) -> Result<Vec<Notice>, String> {
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// How a project changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A new project appeared.
+    Added,
+    /// A project disappeared.
+    Removed,
+    /// A project moved to another directory; holds the old path.
+    Renamed(PathBuf),
+    /// A project's manifest changed; holds the names of the fields that differ.
+    Changed(Vec<String>),
+}
+
+/// A change to the project index.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// What happened.
+    pub change: Change,
+    /// The project as it is now, or as it was for `Change::Removed`.
+    pub info: ProjectInfo,
+    /// When the change was noticed.
+    pub time: SystemTime,
+}
+
+impl Event {
+    /// Returns the name of the change, e.g. `added`.
+    fn label(&self) -> &'static str {
+        match self.change {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Renamed(_) => "renamed",
+            Change::Changed(_) => "changed",
+        }
+    }
+
+    /// Returns the line printed for the event in text mode.
+    pub fn text(&self) -> String {
+        let detail = match &self.change {
+            Change::Renamed(from) => format!(" (from {})", from.display()),
+            Change::Changed(fields) => format!(" ({})", fields.join(", ")),
+            _ => String::new(),
+        };
+        format!("{}  {:<7}  {}  {}{}", util::format_timestamp(self.time), self.label(), self.info.name, self.info.path.display(), detail)
+    }
+
+    /// Returns the event as a table, written as one JSON object per line by `watch`.
+    pub fn record(&self) -> Value {
+        let mut table = Table::new();
+        table.insert("event".into(), Value::String(self.label().into()));
+        table.insert("name".into(), Value::String(self.info.name.clone()));
+        table.insert("path".into(), Value::String(self.info.path.to_string_lossy().into_owned()));
+        table.insert("kind".into(), Value::String(output::kind_name(self.info.kind).into()));
+        table.insert("time".into(), output::time_value(self.time));
+        match &self.change {
+            Change::Renamed(from) => {
+                table.insert("from".into(), Value::String(from.to_string_lossy().into_owned()));
+            }
+            Change::Changed(fields) => {
+                table.insert("fields".into(), Value::Array(fields.iter().cloned().map(Value::String).collect()));
+            }
+            _ => {}
+        }
+        Value::Table(table)
+    }
+}
+
+/// Summarises a batch of events for a status line, e.g. `2 added, 1 changed`.
+pub fn summary(events: &[Event]) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for event in events {
+        *counts.entry(event.label()).or_default() += 1;
+    }
+    let parts: Vec<String> = counts.iter().map(|(label, count)| format!("{} {}", count, label)).collect();
+    parts.join(", ")
+}
+
+/// A project index that follows the filesystem.
+///
+/// Every directory the walk visits is watched, along with the directories of workspace
+/// members. When something changes, only the part of the tree around it is searched again:
+/// the outermost project enclosing the change, or the changed directory itself when it is
+/// not inside a project. Manifests are read through the index cache, so unchanged projects
+/// in that part are not parsed again.
+#[derive(Debug)]
+pub struct Live {
+    /// The inotify watcher.
+    watcher: Watcher,
+    /// The canonical scan roots, in the order their projects take precedence.
+    roots: Vec<PathBuf>,
+    /// The options the index was scanned with.
+    options: ScanOptions,
+    /// The cache of parsed manifests, saved after each batch of changes.
+    cache: Cache,
+    /// The current index.
+    projects: ProjectIndex,
+}
+
+impl Live {
+    /// Starts watching the directories of a scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The scan roots, in the order their projects take precedence.
+    /// * `options` - The options the index was scanned with.
+    /// * `cache` - The cache the index was scanned with.
+    /// * `projects` - The index found by the scan.
+    /// * `dirs` - The canonical paths of the directories the scan visited.
+    pub fn start(roots: &[PathBuf], options: ScanOptions, cache: Cache, projects: ProjectIndex, dirs: &[PathBuf]) -> Result<Live, String> {
+        let mut live = Live {
+            watcher: Watcher::new()?,
+            roots: roots.iter().filter_map(|root| std::fs::canonicalize(root).ok()).collect(),
+            options,
+            cache,
+            projects: ProjectIndex::new(),
+        };
+        live.watch(dirs, &projects);
+        live.projects = projects;
+        Ok(live)
+    }
+
+    /// Returns the current index.
+    pub fn projects(&self) -> &ProjectIndex {
+        &self.projects
+    }
+
+    /// Returns the descriptor that becomes readable when something changed.
+    pub fn fd(&self) -> i32 {
+        self.watcher.fd()
+    }
+
+    /// Waits for changes, updates the index and reports how it changed.
+    ///
+    /// Once something happens, the changes are collected until the filesystem has been
+    /// quiet for a moment, so that e.g. `git checkout` leads to one update.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for the first change, or `None` to wait forever.
+    ///
+    /// # Returns
+    ///
+    /// The changes to the index, sorted by path; empty when nothing relevant changed.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<Event>, String> {
+        let mut notices = self.watcher.read(timeout)?;
+        if notices.is_empty() {
+            return Ok(Vec::new());
+        }
+        let started = Instant::now();
+        while started.elapsed() < MAX_DELAY {
+            let more = self.watcher.read(Some(QUIET))?;
+            if more.is_empty() {
+                break;
+            }
+            notices.extend(more);
+        }
+        Ok(self.apply(&notices))
+    }
+
+    /// Searches the areas affected by the notices again and updates the index.
+    ///
+    /// Like the full scan, the roots are searched in order and a project found under an
+    /// earlier root is kept over the same project found under a later one.
+    fn apply(&mut self, notices: &[Notice]) -> Vec<Event> {
+        let mut areas: Vec<(usize, PathBuf, usize)> = if notices.contains(&Notice::Overflow) {
+            (0..self.roots.len()).map(|root| (root, self.roots[root].clone(), 0)).collect()
+        } else {
+            notices.iter().flat_map(|notice| self.areas(notice)).collect()
+        };
+        // Searching an area also covers every area below it from the same root
+        areas.sort();
+        areas.dedup_by(|later, earlier| later.0 == earlier.0 && later.1.starts_with(&earlier.1));
+
+        let mut removed = ProjectIndex::new();
+        for (_, area, _) in &areas {
+            let stale: Vec<PathBuf> = self.projects.range(area.clone()..).map(|(path, _)| path).take_while(|path| path.starts_with(area)).cloned().collect();
+            for path in stale {
+                if let Some(info) = self.projects.remove(&path) {
+                    removed.insert(path, info);
+                }
+            }
+        }
+        let mut added = ProjectIndex::new();
+        for (root, area, depth) in areas {
+            if area.is_dir() {
+                let (found, dirs) = walk(&self.roots[root], &area, depth, &self.options, &self.cache);
+                self.watch(&dirs, &found);
+                for (path, info) in found {
+                    if let btree_map::Entry::Vacant(entry) = self.projects.entry(path.clone()) {
+                        added.insert(path, info.clone());
+                        entry.insert(info);
+                    }
+                }
+            }
+        }
+        if let Err(message) = self.cache.save() {
+            eprintln!("{}", message);
+        }
+        diff(removed, added)
+    }
+
+    /// Works out which parts of the tree a notice calls for searching again.
+    ///
+    /// A changed directory is searched again, and so is the package of a changed file that
+    /// the index is read from: `Cargo.toml`, `Cargo.lock`, or a source file Cargo discovers
+    /// targets from, such as `src/main.rs` or `examples/demo.rs`.
+    ///
+    /// # Returns
+    ///
+    /// For every scan root holding the change, in order: the index of the root, the directory
+    /// to search from and its depth below the root. Empty if the notice cannot change the
+    /// index, e.g. a module being saved.
+    fn areas(&self, notice: &Notice) -> Vec<(usize, PathBuf, usize)> {
+        let Notice::Entry { path, is_dir } = notice else {
+            return Vec::new();
+        };
+        let changed = if *is_dir { Some(path.as_path()) } else { package_of(path) };
+        let Some(changed) = changed else {
+            return Vec::new();
+        };
+        (0..self.roots.len()).filter_map(|root| self.area(root, changed)).collect()
+    }
+
+    /// Works out the area to search again below one scan root for `areas`.
+    fn area(&self, root_index: usize, changed: &Path) -> Option<(usize, PathBuf, usize)> {
+        let root = &self.roots[root_index];
+        if !changed.starts_with(root) {
+            return None;
+        }
+
+        // Nothing below a skipped directory is indexed
+        let below_root: Vec<&Path> = changed.ancestors().take_while(|dir| dir != root).collect();
+        if below_root.iter().any(|dir| is_skipped_dir(dir, root, &self.options)) {
+            return None;
+        }
+
+        // The outermost project, from the root down, holds the change; a project that was just
+        // deleted no longer has a manifest but is still in the index
+        let area = std::iter::once(root.as_path())
+            .chain(below_root.into_iter().rev())
+            .find(|dir| dir.join("Cargo.toml").is_file() || self.projects.contains_key(*dir))
+            .unwrap_or(changed)
+            .to_path_buf();
+        let depth = area.strip_prefix(root).map_or(0, |relative| relative.components().count());
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return None;
+        }
+        Some((root_index, area, depth))
+    }
+
+    /// Watches the visited directories, and the directories of workspace members and the
+    /// directories holding them, so that members being added, removed or edited is noticed.
+    fn watch(&mut self, dirs: &[PathBuf], projects: &ProjectIndex) {
+        for dir in dirs {
+            self.watcher.add(dir);
+        }
+        for member in projects.values().flat_map(|info| &info.members) {
+            if let Ok(path) = std::fs::canonicalize(&member.path) {
+                if let Some(parent) = path.parent() {
+                    self.watcher.add(parent);
+                }
+                self.watcher.add(&path);
+            }
+        }
+    }
+}
+
+/// Returns the package directory of a changed file that the index is read from.
+///
+/// These are `Cargo.toml` and `Cargo.lock`, `src/main.rs` and `src/lib.rs`, and the `*.rs`
+/// and `*/main.rs` files in `src/bin/`, `examples/`, `tests/` and `benches/`, from which Cargo
+/// discovers targets.
+fn package_of(file: &Path) -> Option<&Path> {
+    let name = file.file_name()?.to_str()?;
+    let parent = file.parent()?;
+    if name == "Cargo.toml" || name == "Cargo.lock" {
+        return Some(parent);
+    }
+    if !name.ends_with(".rs") {
+        return None;
+    }
+    if (name == "main.rs" || name == "lib.rs") && parent.ends_with("src") {
+        return parent.parent();
+    }
+    let target_dirs = std::iter::once(parent).chain(parent.parent().filter(|_| name == "main.rs"));
+    for dir in target_dirs {
+        if let Some(target_dir) = TARGET_DIRS.iter().find(|target_dir| dir.ends_with(target_dir)) {
+            return dir.ancestors().nth(Path::new(target_dir).components().count());
+        }
+    }
+    None
+}
+
+/// Compares the projects that were in the searched areas before and after.
+///
+/// A project that disappeared from one place and appeared in another under the same name is
+/// reported as renamed.
+fn diff(mut removed: ProjectIndex, mut added: ProjectIndex) -> Vec<Event> {
+    let time = SystemTime::now();
+    let mut events = Vec::new();
+
+    let kept: Vec<PathBuf> = removed.keys().filter(|path| added.contains_key(*path)).cloned().collect();
+    for path in kept {
+        let (old, new) = (removed.remove(&path).expect("kept project"), added.remove(&path).expect("kept project"));
+        if old != new {
+            let fields = changed_fields(&old, &new);
+            if !fields.is_empty() {
+                events.push(Event { change: Change::Changed(fields), info: new, time });
+            }
+        }
+    }
+
+    for info in added.into_values() {
+        let moved_from = removed
+            .iter()
+            .find(|(_, old)| old.name == info.name && old.kind == info.kind)
+            .map(|(old_path, _)| old_path.clone());
+        match moved_from {
+            Some(from) => {
+                removed.remove(&from);
+                events.push(Event { change: Change::Renamed(from), info, time });
+            }
+            None => events.push(Event { change: Change::Added, info, time }),
+        }
+    }
+    events.extend(removed.into_values().map(|info| Event { change: Change::Removed, info, time }));
+    events.sort_by(|a, b| a.info.path.cmp(&b.info.path));
+    events
+}
+
+/// Lists the record fields that differ between two versions of a project.
+///
+/// Built binaries are left out, since they change without the manifest changing.
+fn changed_fields(old: &ProjectInfo, new: &ProjectInfo) -> Vec<String> {
+    let (old, new) = (output::project_record(old, false), output::project_record(new, false));
+    let (Some(old), Some(new)) = (old.as_table(), new.as_table()) else {
+        return Vec::new();
+    };
+    let mut fields: Vec<String> = old.keys().chain(new.keys()).filter(|key| *key != "artifacts" && old.get(*key) != new.get(*key)).cloned().collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+/// Picks the projects to browse from an index, in listing order.
+type Select<'a> = Box<dyn Fn(&ProjectIndex) -> Vec<ProjectInfo> + 'a>;
+
+/// A project list that follows the filesystem, for the full-screen browser.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct LiveList<'a> {
+    /// The index kept up to date.
+    live: Live,
+    /// Picks the projects to browse from the index, in listing order.
+    select: Select<'a>,
+}
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+impl<'a> LiveList<'a> {
+    /// Creates a live list from a watcher and the function that picks the browsed projects.
+    pub fn new(live: Live, select: impl Fn(&ProjectIndex) -> Vec<ProjectInfo> + 'a) -> Self {
+        LiveList { live, select: Box::new(select) }
+    }
+
+    /// Returns the descriptor that becomes readable when something changed.
+    pub fn fd(&self) -> i32 {
+        self.live.fd()
+    }
+
+    /// Applies pending changes without waiting for more.
+    ///
+    /// # Returns
+    ///
+    /// The new list of projects and a summary of what changed, or `None` if nothing did.
+    pub fn update(&mut self) -> Option<(Vec<ProjectInfo>, String)> {
+        match self.live.wait(Some(Duration::ZERO)) {
+            Ok(events) if !events.is_empty() => Some(((self.select)(self.live.projects()), summary(&events))),
+            Ok(_) => None,
+            Err(message) => Some(((self.select)(self.live.projects()), message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::util::{scratch_dir, write_file};
+
+    fn write_package(dir: &Path, name: &str) {
+        write_file(&dir.join("Cargo.toml"), &format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name));
+    }
+
+    #[test]
+    fn files_the_index_is_read_from() {
+        let package = |file: &str| package_of(Path::new(file)).map(|dir| dir.display().to_string());
+        assert_eq!(package("/p/Cargo.toml"), Some("/p".to_string()));
+        assert_eq!(package("/p/Cargo.lock"), Some("/p".to_string()));
+        assert_eq!(package("/p/src/main.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/src/lib.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/src/bin/tool.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/src/bin/tool/main.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/examples/demo.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/benches/speed/main.rs"), Some("/p".to_string()));
+        assert_eq!(package("/p/src/util.rs"), None);
+        assert_eq!(package("/p/src/bin/tool/args.rs"), None);
+        assert_eq!(package("/p/README.md"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn notices_search_each_root_holding_the_change() {
+        let dir = scratch_dir("watch-apply");
+        let outer = dir.join("outer");
+        let inner = outer.join("inner");
+        write_package(&outer.join("app"), "app");
+        fs::create_dir_all(&inner).expect("create the inner root");
+        let roots = [outer.clone(), inner.clone()];
+        let options = ScanOptions { max_depth: Some(1), ..ScanOptions::default() };
+        let mut live = Live {
+            watcher: Watcher::new().expect("inotify is available"),
+            roots: roots.to_vec(),
+            options,
+            cache: Cache::at(dir.join("index.toml")),
+            projects: ProjectIndex::new(),
+        };
+        let entry = |path: PathBuf, is_dir: bool| Notice::Entry { path, is_dir };
+        let names = |events: &[Event]| events.iter().map(|event| (event.info.name.clone(), event.change.clone())).collect::<Vec<_>>();
+
+        // `lib` is too deep below the outer root, but one level below the inner one
+        write_package(&inner.join("lib"), "lib");
+        let events = live.apply(&[entry(outer.clone(), true), entry(inner.join("lib/Cargo.toml"), false)]);
+        assert_eq!(names(&events), [("app".to_string(), Change::Added), ("lib".to_string(), Change::Added)]);
+
+        // Files the index does not depend on are ignored; a new binary is picked up
+        write_file(&inner.join("lib/README.md"), "# lib\n");
+        assert!(live.apply(&[entry(inner.join("lib/README.md"), false)]).is_empty());
+        write_file(&inner.join("lib/src/main.rs"), "fn main() {}\n");
+        let events = live.apply(&[entry(inner.join("lib/src/main.rs"), false)]);
+        assert_eq!(names(&events), [("lib".to_string(), Change::Changed(vec!["targets".to_string()]))]);
+
+        fs::remove_dir_all(inner.join("lib")).expect("remove the package");
+        let events = live.apply(&[entry(inner.join("lib"), true)]);
+        assert_eq!(names(&events), [("lib".to_string(), Change::Removed)]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Returns the member crate directories of a `[workspace]` table.
+///
+/// The `members` entries are expanded as globs relative to the workspace root, and any
+/// directory matched by an `exclude` entry is removed. The workspace root itself is never
+/// returned as a member, since it is already listed as the parent entry.
+///
+/// # Arguments
+///
+/// * `workspace` - The `[workspace]` table from the root `Cargo.toml`.
+/// * `root` - The directory containing the root `Cargo.toml`.
+///
+/// # Returns
+///
+/// A sorted list of member directories that contain a `Cargo.toml`.
+pub fn member_dirs(workspace: &Value, root: &Path) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = string_list(workspace, "exclude")
+        .iter()
+        .flat_map(|pattern| expand_glob(root, pattern))
+        .collect();
+
+    let mut members: Vec<PathBuf> = string_list(workspace, "members")
+        .iter()
+        .flat_map(|pattern| expand_glob(root, pattern))
+        .filter(|dir| dir != root && !excluded.contains(dir))
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .collect();
+    members.sort();
+    members.dedup();
+    members
+}
+
+/// Lists the paths whose entries decide which members a `[workspace]` table has.
+///
+/// These are the directories every `members` and `exclude` glob is expanded from, and every
+/// path a glob names or matches, whether it exists or not, so that creating a member
+/// directory, or a `Cargo.toml` in one, changes one of them.
+///
+/// # Arguments
+///
+/// * `workspace` - The `[workspace]` table from the root `Cargo.toml`.
+/// * `root` - The directory containing the root `Cargo.toml`.
+///
+/// # Returns
+///
+/// The paths, sorted and without duplicates.
+pub fn member_inputs(workspace: &Value, root: &Path) -> Vec<PathBuf> {
+    let mut read = Vec::new();
+    for pattern in string_list(workspace, "members").iter().chain(&string_list(workspace, "exclude")) {
+        let candidates = expand_pattern(root, pattern, &mut read);
+        read.extend(candidates);
+    }
+    read.sort();
+    read.dedup();
+    read
+}
+
+/// Reads an array of strings from a TOML table, ignoring entries that are not strings.
+fn string_list(table: &Value, key: &str) -> Vec<String> {
+    table
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|i| i.as_str()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Expands a workspace glob pattern such as `crates/*` into matching directories.
+///
+/// Each `/`-separated component of the pattern is matched against the directory entries
+/// at that level, so wildcards never cross a path separator.
+///
+/// # Arguments
+///
+/// * `root` - The directory the pattern is relative to.
+/// * `pattern` - The glob pattern, using `*` and `?` wildcards.
+///
+/// # Returns
+///
+/// The directories that match the pattern.
+fn expand_glob(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    expand_pattern(root, pattern, &mut Vec::new()).into_iter().filter(|dir| dir.is_dir()).collect()
+}
+
+/// Expands a glob pattern into the paths it could name, which may not exist.
+///
+/// # Arguments
+///
+/// * `root` - The directory the pattern is relative to.
+/// * `pattern` - The glob pattern, using `*` and `?` wildcards.
+/// * `read` - Collects every path the expansion depends on: the directories whose entries
+///   were listed, and the intermediate paths the pattern names.
+fn expand_pattern(root: &Path, pattern: &str, read: &mut Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut matches = vec![root.to_path_buf()];
+
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        let mut next = Vec::new();
+        for dir in &matches {
+            read.push(dir.clone());
+            if component == ".." {
+                next.push(dir.join(".."));
+            } else if !component.contains(['*', '?']) {
+                next.push(dir.join(component));
+            } else if let Ok(entries) = fs::read_dir(dir) {
+                let mut found: Vec<PathBuf> = entries
+                    .filter_map(Result::ok)
+                    .filter(|e| glob_match(component, &e.file_name().to_string_lossy()))
+                    .map(|e| e.path())
+                    .collect();
+                found.sort();
+                next.extend(found);
+            }
+        }
+        matches = next;
+    }
+    matches
+}
+
+/// Matches a single path component against a pattern containing `*` and `?` wildcards.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern, where `*` matches any run of characters and `?` matches one.
+/// * `text` - The text to match.
+///
+/// # Returns
+///
+/// `true` if the whole of `text` matches `pattern`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Finds the root directory of the workspace enclosing a crate directory.
+///
+/// The crate directory and each of its ancestors are checked for a `Cargo.toml` with a
+/// `[workspace]` table, the same way Cargo locates the workspace root of a member.
+///
+/// # Arguments
+///
+/// * `dir` - The directory containing the member crate's `Cargo.toml`.
+///
+/// # Returns
+///
+/// The nearest workspace root, which is `dir` itself for a workspace root.
+pub fn enclosing_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| read_workspace(ancestor).is_some())
+        .map(Path::to_path_buf)
+}
+
+/// Finds the `[workspace]` table of the workspace enclosing a crate directory.
+///
+/// # Arguments
+///
+/// * `dir` - The directory containing the member crate's `Cargo.toml`.
+///
+/// # Returns
+///
+/// The `[workspace]` table of the nearest workspace root together with the root directory,
+/// which the paths in `[workspace.dependencies]` are relative to, if one is found.
+pub fn enclosing_workspace(dir: &Path) -> Option<(Value, PathBuf)> {
+    dir.ancestors()
+        .find_map(|ancestor| Some((read_workspace(ancestor)?, ancestor.to_path_buf())))
+}
+
+/// Reads the `[workspace]` table of the `Cargo.toml` in a directory, if it has one.
+fn read_workspace(dir: &Path) -> Option<Value> {
+    let manifest: Value = fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok()?;
+    manifest.get("workspace").cloned()
+}
+
+/// Returns `true` if a manifest field is written as `{ workspace = true }`.
+pub fn is_inherited(value: &Value) -> bool {
+    value.get("workspace").and_then(|w| w.as_bool()).unwrap_or(false)
+}
+
+/// Resolves a `[package]` field, following `{ workspace = true }` to the workspace's value.
+///
+/// # Arguments
+///
+/// * `package` - The member crate's `[package]` table.
+/// * `key` - The name of the field to resolve.
+/// * `workspace_package` - The `[workspace.package]` table of the enclosing workspace, if any.
+///
+/// # Returns
+///
+/// The resolved value together with `true` if it was inherited from the workspace, or `None`
+/// if the field is missing or the workspace does not define it.
+pub fn resolve_field<'a>(package: &'a Value, key: &str, workspace_package: Option<&'a Value>) -> Option<(&'a Value, bool)> {
+    let value = package.get(key)?;
+    if is_inherited(value) {
+        workspace_package?.get(key).map(|v| (v, true))
+    } else {
+        Some((value, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{scratch_dir, write_file};
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("crate-*", "crate-a"));
+        assert!(glob_match("crate-*", "crate-"));
+        assert!(!glob_match("crate-*", "crates"));
+        assert!(glob_match("?x", "ax"));
+        assert!(!glob_match("?x", "x"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*-old", "api-old"));
+        assert!(!glob_match("*-old", "api-old-2"));
+        assert!(glob_match("zoë*", "zoë-crate"));
+    }
+
+    #[test]
+    fn members_expand_from_globs() {
+        let root = scratch_dir("workspace-members");
+        for member in ["crates/a", "crates/b", "crates/old-c", "tools/gen", "loose"] {
+            write_file(&root.join(member).join("Cargo.toml"), "[package]\nname = \"m\"\n");
+        }
+        // A matching directory without a manifest is not a member
+        fs::create_dir_all(root.join("crates/empty")).expect("create fixture directory");
+        write_file(&root.join("crates/file.txt"), "\n");
+
+        let workspace: Value = "members = [\"crates/*\", \"./tools/*\", \"loose\", \"missing\", \".\"]\nexclude = [\"crates/old-*\"]"
+            .parse()
+            .expect("valid workspace table");
+        let names: Vec<String> = member_dirs(&workspace, &root)
+            .iter()
+            .map(|dir| dir.strip_prefix(&root).expect("member below the root").display().to_string())
+            .collect();
+        assert_eq!(names, ["crates/a", "crates/b", "loose", "tools/gen"]);
+
+        let expanded = expand_glob(&root, "crates/*");
+        let crates = ["a", "b", "empty", "old-c"].map(|name| root.join("crates").join(name));
+        assert_eq!(expanded, crates, "globs match directories only");
+        assert!(expand_glob(&root, "nothing/*").is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn inherited_fields() {
+        let package: Value = "version.workspace = true\nedition = \"2021\"\nlicense = { workspace = true }\nreadme.workspace = false"
+            .parse()
+            .expect("valid package table");
+        let workspace_package: Value = "version = \"1.2.0\"\nedition = \"2018\"".parse().expect("valid workspace table");
+        let resolve = |key: &str| resolve_field(&package, key, Some(&workspace_package)).map(|(v, inherited)| (v.clone(), inherited));
+
+        assert_eq!(resolve("version"), Some((Value::String("1.2.0".into()), true)));
+        assert_eq!(resolve("edition"), Some((Value::String("2021".into()), false)), "own values win");
+        assert_eq!(resolve("license"), None, "the workspace does not define it");
+        assert_eq!(resolve("description"), None);
+        assert!(resolve("readme").is_some_and(|(_, inherited)| !inherited));
+        assert_eq!(resolve_field(&package, "version", None), None);
+    }
+}